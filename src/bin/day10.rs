@@ -1,13 +1,37 @@
 use advent_of_code_2023::coordinates::{Coordinate, Grid, Offset};
 use anyhow::{anyhow, Error, Result};
-use std::fmt::Display;
+use std::{collections::VecDeque, fmt::Display, iter::repeat_n};
+use strum::{EnumIter, IntoEnumIterator};
+use thiserror::Error;
 use winnow::{
     ascii::line_ending,
     combinator::{alt, repeat, separated},
     prelude::*,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Error, PartialEq, Eq)]
+enum MazeError {
+    #[error("maze has no start")]
+    NoStart,
+    #[error("maze has more than one start, at {}", join(.0))]
+    MultipleStarts(Vec<Coordinate>),
+    #[error("start at {0} connects to {1} neighbours instead of 2")]
+    StartConnections(Coordinate, usize),
+    #[error("loop leaves the maze heading {1:?} from {0}")]
+    OutOfBounds(Coordinate, Direction),
+    #[error("loop is broken at {0}, which cannot be entered heading {1:?}")]
+    DeadEnd(Coordinate, Direction),
+}
+
+fn join(coordinates: &[Coordinate]) -> String {
+    coordinates
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
 enum Direction {
     North,
     East,
@@ -73,6 +97,13 @@ impl Node {
             }
         }
     }
+
+    fn connections(self) -> Vec<Direction> {
+        match self {
+            Node::Empty | Node::Start => vec![],
+            Node::Path(x, y) => vec![x, y],
+        }
+    }
 }
 
 impl Display for Node {
@@ -95,79 +126,322 @@ impl Display for Node {
                 | Self::Path(Direction::East, Direction::North) => '\u{255A}',
                 Self::Path(Direction::North, Direction::West)
                 | Self::Path(Direction::West, Direction::North) => '\u{255D}',
-                Self::Path(_, _) => unimplemented!(),
+                Self::Path(_, _) => '?',
             }
         )
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Region {
+    Loop,
+    Inside,
+    Outside,
+}
+
 #[derive(Debug)]
-struct Maze(Grid<Node>);
+struct Maze {
+    grid: Grid<Node>,
+    start: Coordinate,
+    // Tiles of the loop, ending at the start, and the direction each one is entered in
+    path: Vec<Coordinate>,
+    directions: Vec<Direction>,
+}
 
 impl Maze {
-    fn start_point(&self) -> Option<Coordinate> {
-        self.0.find(&Node::Start)
+    // Replaces the start with the pipe connecting its two connected neighbours, and checks
+    // that it is part of a closed loop
+    fn new(mut grid: Grid<Node>) -> Result<Self, MazeError> {
+        let starts: Vec<_> = grid
+            .0
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, node)| **node == Node::Start)
+                    .map(move |(x, _)| Coordinate::new(x, y))
+            })
+            .collect();
+        let start = match starts[..] {
+            [] => return Err(MazeError::NoStart),
+            [start] => start,
+            _ => return Err(MazeError::MultipleStarts(starts)),
+        };
+
+        let connected: Vec<_> = Direction::iter()
+            .filter(|direction| {
+                start
+                    .offset((*direction).into())
+                    .and_then(|neighbour| grid.get(neighbour))
+                    .and_then(|node| node.walk(*direction))
+                    .is_some()
+            })
+            .collect();
+        let [x, y] = connected[..] else {
+            return Err(MazeError::StartConnections(start, connected.len()));
+        };
+        *grid.get_mut(start).unwrap() = Node::Path(x, y);
+
+        let (path, directions) = Self::find_loop(&grid, start)?;
+        Ok(Self {
+            grid,
+            start,
+            path,
+            directions,
+        })
     }
 
-    fn find_length(&self) -> Option<(Vec<Coordinate>, Vec<Direction>)> {
-        let start = self.start_point()?;
-        let mut current = start.iter(false).find(|coord| match self.0.get(*coord) {
-            Some(Node::Empty | Node::Start) | None => false,
-            Some(Node::Path(x, y)) => {
-                Offset::from(*x) == (start - *coord) || Offset::from(*y) == (start - *coord)
+    fn find_loop(
+        grid: &Grid<Node>,
+        start: Coordinate,
+    ) -> Result<(Vec<Coordinate>, Vec<Direction>), MazeError> {
+        let Some(Node::Path(mut direction, _)) = grid.get(start).copied() else {
+            return Err(MazeError::StartConnections(start, 0));
+        };
+        let mut current = start;
+        let mut coordinates = vec![];
+        let mut directions = vec![];
+        loop {
+            let next = current
+                .offset(direction.into())
+                .filter(|next| grid.get(*next).is_some())
+                .ok_or(MazeError::OutOfBounds(current, direction))?;
+            current = next;
+            coordinates.push(current);
+            directions.push(direction);
+            if current == start {
+                return Ok((coordinates, directions));
             }
-        })?;
+            direction = grid
+                .get(current)
+                .and_then(|node| node.walk(direction))
+                .ok_or(MazeError::DeadEnd(current, direction))?;
+        }
+    }
 
-        let mut current_node = self.0.get(current)?;
-        let mut prev_direction = (current - start).try_into().ok()?;
-        Some(
-            [(current, prev_direction)]
-                .into_iter()
-                .chain(std::iter::from_fn(move || {
-                    prev_direction = current_node.walk(prev_direction)?;
-                    current = current.offset(prev_direction.into())?;
-                    current_node = self.0.get(current)?;
-                    Some((current, prev_direction))
-                }))
-                .unzip(),
+    // Steps from the start to every pipe reachable by following connected pipes
+    fn distances(&self) -> Grid<Option<usize>> {
+        let mut distances = Grid(
+            self.grid
+                .0
+                .iter()
+                .map(|row| vec![None; row.len()])
+                .collect(),
+        );
+        *distances.get_mut(self.start).unwrap() = Some(0);
+        let mut queue = VecDeque::from([(self.start, 0)]);
+        while let Some((current, distance)) = queue.pop_front() {
+            let Some(node) = self.grid.get(current) else {
+                continue;
+            };
+            for direction in node.connections() {
+                let Some(next) = current.offset(direction.into()) else {
+                    continue;
+                };
+                let connected = self
+                    .grid
+                    .get(next)
+                    .is_some_and(|node| node.walk(direction).is_some());
+                match distances.get_mut(next) {
+                    Some(entry @ None) if connected => {
+                        *entry = Some(distance + 1);
+                        queue.push_back((next, distance + 1));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        distances
+    }
+
+    // Classifies every tile by counting the loop crossings to its left on the same row
+    fn regions(&self) -> Grid<Region> {
+        let mut entered = Grid(
+            self.grid
+                .0
+                .iter()
+                .map(|row| vec![None; row.len()])
+                .collect(),
+        );
+        for (coordinate, direction) in self.path.iter().zip(&self.directions) {
+            if let Some(entry) = entered.get_mut(*coordinate) {
+                *entry = Some(*direction);
+            }
+        }
+
+        Grid(
+            entered
+                .0
+                .iter()
+                .enumerate()
+                .map(|(y, row)| {
+                    let mut num = 0;
+                    row.iter()
+                        .enumerate()
+                        .map(|(x, dir)| match dir {
+                            Some(dir) => {
+                                if self.grid.get(Coordinate::new(x, y)).is_some_and(|node| {
+                                    node.connections().contains(&Direction::South)
+                                }) {
+                                    num += if *dir == Direction::North { 1 } else { -1 };
+                                }
+                                Region::Loop
+                            }
+                            None if num != 0 => Region::Inside,
+                            None => Region::Outside,
+                        })
+                        .collect()
+                })
+                .collect(),
         )
     }
 
-    fn count_enclosed(&self, coordinates: &[Coordinate], directions: &[Direction]) -> usize {
-        let mut i = 0;
-        for (y, row) in self.0 .0.iter().enumerate() {
-            let mut num = 0;
-            for (x, _) in row.iter().enumerate() {
-                if let Some(index) = coordinates
+    // Draws only the loop, with enclosed tiles as 'I' and outside tiles as 'O'
+    fn render_regions(&self, regions: &Grid<Region>) -> String {
+        self.grid
+            .0
+            .iter()
+            .zip(&regions.0)
+            .map(|(nodes, regions)| {
+                nodes
                     .iter()
-                    .position(|coord| *coord == Coordinate::new(x, y))
-                {
-                    let dir = directions[index];
-                    let node = self.0.get(coordinates[index]).unwrap();
-                    match node {
-                        Node::Path(_, Direction::South) | Node::Path(Direction::South, _) => {
-                            if dir == Direction::North {
-                                num += 1;
-                            } else {
-                                num -= 1;
-                            }
-                        }
-                        _ => {}
-                    }
-                } else if num != 0 {
-                    i += 1;
+                    .zip(regions)
+                    .map(|(node, region)| match region {
+                        Region::Loop => node.to_string(),
+                        Region::Inside => "I".to_owned(),
+                        Region::Outside => "O".to_owned(),
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn to_svg(&self, regions: &Grid<Region>) -> String {
+        const TILE: usize = 10;
+        let height = regions.0.len();
+        let width = regions.0.first().map_or(0, Vec::len);
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+            width * TILE,
+            height * TILE
+        );
+        for (y, row) in regions.0.iter().enumerate() {
+            for (x, region) in row.iter().enumerate() {
+                let fill = match region {
+                    Region::Loop => continue,
+                    Region::Inside => "#4caf50",
+                    Region::Outside => "#e0e0e0",
+                };
+                svg += &format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{TILE}\" height=\"{TILE}\" fill=\"{fill}\"/>\n",
+                    x * TILE,
+                    y * TILE
+                );
+            }
+        }
+        let points = self
+            .path
+            .iter()
+            .map(|coord| {
+                format!(
+                    "{},{}",
+                    coord.x * TILE + TILE / 2,
+                    coord.y * TILE + TILE / 2
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        svg += &format!(
+            "<polygon points=\"{points}\" fill=\"none\" stroke=\"#212121\" stroke-width=\"2\"/>\n</svg>\n"
+        );
+        svg
+    }
+
+    // Binary PPM image with every tile drawn as 3x3 pixels
+    fn to_ppm(&self, regions: &Grid<Region>) -> Vec<u8> {
+        let walls = self.upscale();
+        let pixels = walls.0.iter().enumerate().flat_map(|(y, row)| {
+            row.iter().enumerate().flat_map(move |(x, wall)| {
+                match (wall, regions.get(Coordinate::new(x / 3, y / 3))) {
+                    (true, _) => [0x21; 3],
+                    (false, Some(Region::Inside)) => [0x4c, 0xaf, 0x50],
+                    (false, Some(Region::Outside)) => [0xe0; 3],
+                    (false, _) => [0xff; 3],
+                }
+            })
+        });
+        let height = walls.0.len();
+        let width = walls.0.first().map_or(0, Vec::len);
+        let mut ppm = format!("P6\n{width} {height}\n255\n").into_bytes();
+        ppm.extend(pixels);
+        ppm
+    }
+
+    // Every tile as 3x3 cells, with the loop drawn as walls through the centres
+    fn upscale(&self) -> Grid<bool> {
+        let mut walls = Grid(
+            self.grid
+                .0
+                .iter()
+                .flat_map(|row| repeat_n(vec![false; row.len() * 3], 3))
+                .collect(),
+        );
+        for coordinate in &self.path {
+            let centre = Coordinate::new(coordinate.x * 3 + 1, coordinate.y * 3 + 1);
+            let connections = self
+                .grid
+                .get(*coordinate)
+                .map_or(vec![], |node| node.connections());
+            for offset in [Offset::new(0, 0)]
+                .into_iter()
+                .chain(connections.into_iter().map(Offset::from))
+            {
+                if let Some(wall) = centre.offset(offset).and_then(|c| walls.get_mut(c)) {
+                    *wall = true;
                 }
             }
         }
-        i
+        walls
+    }
+
+    // Alternative to count_enclosed, flooding the upscaled maze from its corner so that
+    // the outside squeezes between adjacent pipes
+    fn count_enclosed_flood(&self) -> usize {
+        let walls = self.upscale();
+        let mut outside = Grid(walls.0.iter().map(|row| vec![false; row.len()]).collect());
+        let corner = Coordinate::new(0, 0);
+        let mut stack = vec![corner];
+        if let Some(visited) = outside.get_mut(corner) {
+            *visited = true;
+        }
+        while let Some(current) = stack.pop() {
+            for next in current.iter(false) {
+                if walls.get(next) == Some(&false) && outside.get(next) == Some(&false) {
+                    *outside.get_mut(next).unwrap() = true;
+                    stack.push(next);
+                }
+            }
+        }
+
+        self.grid
+            .0
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| (0..row.len()).map(move |x| Coordinate::new(x * 3 + 1, y * 3 + 1)))
+            .filter(|centre| {
+                walls.get(*centre) == Some(&false) && outside.get(*centre) == Some(&false)
+            })
+            .count()
     }
 }
 
 impl Display for Maze {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let out_string = self
+            .grid
             .0
-             .0
             .iter()
             .map(|row| row.iter().map(ToString::to_string).collect())
             .collect::<Vec<String>>()
@@ -176,6 +450,55 @@ impl Display for Maze {
     }
 }
 
+fn count_enclosed(regions: &Grid<Region>) -> usize {
+    regions
+        .0
+        .iter()
+        .flatten()
+        .filter(|region| **region == Region::Inside)
+        .count()
+}
+
+fn farthest(distances: &Grid<Option<usize>>) -> Option<(Coordinate, usize)> {
+    distances
+        .0
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter_map(move |(x, distance)| Some((Coordinate::new(x, y), (*distance)?)))
+        })
+        .max_by_key(|(_, distance)| *distance)
+}
+
+// Distances scaled to the digits 0-9, with unreached tiles left blank
+fn render_distances(distances: &Grid<Option<usize>>) -> String {
+    let max = distances
+        .0
+        .iter()
+        .flatten()
+        .flatten()
+        .max()
+        .copied()
+        .unwrap_or(0);
+    distances
+        .0
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|distance| match distance {
+                    Some(distance) => {
+                        char::from_digit((distance * 9 / max.max(1)) as u32, 10).unwrap_or('9')
+                    }
+                    None => ' ',
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 fn parse_nodes(input: &mut &str) -> PResult<Vec<Node>> {
     repeat(
         1..,
@@ -193,27 +516,173 @@ fn parse_nodes(input: &mut &str) -> PResult<Vec<Node>> {
     .parse_next(input)
 }
 
-fn parser(input: &mut &str) -> PResult<Maze> {
+fn parser(input: &mut &str) -> PResult<Grid<Node>> {
     separated(1.., parse_nodes, line_ending)
-        .map(|maze| Maze(Grid(maze)))
+        .map(Grid)
         .parse_next(input)
 }
 
 fn parse(input: &str) -> Result<Maze> {
-    parser.parse(input).map_err(|e| anyhow!(e.to_string()))
+    Ok(Maze::new(
+        parser.parse(input).map_err(|e| anyhow!(e.to_string()))?,
+    )?)
 }
 
 fn main() -> Result<()> {
     let input = include_str!("../../input/day10.txt");
     let maze = parse(input)?;
     println!("{maze}");
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    let args: Vec<_> = args.iter().map(String::as_str).collect();
 
-    let (path, directions) = maze.find_length().unwrap();
-    println!("Furthest distance from start: {}", path.len() / 2);
+    let distances = maze.distances();
+    if args == ["heatmap"] {
+        println!("{}", render_distances(&distances));
+    }
+    let (coordinate, distance) =
+        farthest(&distances).ok_or(anyhow!("no pipes reachable from the start"))?;
+    println!("Furthest distance from start: {distance} at {coordinate}");
 
+    let regions = maze.regions();
+    match args[..] {
+        ["render"] => println!("{}", maze.render_regions(&regions)),
+        ["render", "svg", file] => std::fs::write(file, maze.to_svg(&regions))?,
+        ["render", "ppm", file] => std::fs::write(file, maze.to_ppm(&regions))?,
+        _ => {}
+    }
+
+    println!("Enclosed spaces: {}", count_enclosed(&regions));
     println!(
-        "Enclosed spaces: {}",
-        maze.count_enclosed(&path, &directions)
+        "Enclosed spaces by flood fill: {}",
+        maze.count_enclosed_flood()
     );
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{count_enclosed, farthest, parse, parser, Direction, Maze, MazeError, Region};
+    use advent_of_code_2023::coordinates::Coordinate;
+    use anyhow::{anyhow, Result};
+    use winnow::Parser;
+
+    fn validate(input: &str) -> Result<Result<Maze, MazeError>> {
+        let grid = parser.parse(input).map_err(|e| anyhow!(e.to_string()))?;
+        Ok(Maze::new(grid))
+    }
+
+    #[test]
+    fn invalid_mazes() -> Result<()> {
+        assert_eq!(validate("F7\nLJ")?.unwrap_err(), MazeError::NoStart);
+        assert_eq!(
+            validate("S7\nLS")?.unwrap_err(),
+            MazeError::MultipleStarts(vec![Coordinate::new(0, 0), Coordinate::new(1, 1)])
+        );
+        assert_eq!(
+            validate("S-\n..")?.unwrap_err(),
+            MazeError::StartConnections(Coordinate::new(0, 0), 1)
+        );
+        assert_eq!(
+            validate("S-7\n|.|\nL-.")?.unwrap_err(),
+            MazeError::DeadEnd(Coordinate::new(2, 2), Direction::South)
+        );
+        assert_eq!(
+            validate("S-7\n|.|\nL-|")?.unwrap_err(),
+            MazeError::OutOfBounds(Coordinate::new(2, 2), Direction::South)
+        );
+        assert_eq!(
+            validate("S-7\n|.|\nL-J")?.map(|maze| maze.path.len()),
+            Ok(8)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn distances() -> Result<()> {
+        let maze = parse("-L|F7\n7S-7|\nL|7||\n-L-J|\nL|-JF")?;
+        let distances = maze.distances();
+        assert_eq!(distances.get(Coordinate::new(1, 1)), Some(&Some(0)));
+        assert_eq!(distances.get(Coordinate::new(2, 1)), Some(&Some(1)));
+        assert_eq!(distances.get(Coordinate::new(1, 3)), Some(&Some(2)));
+        // Pipes that are not part of the loop are never reached
+        assert_eq!(distances.get(Coordinate::new(0, 0)), Some(&None));
+        assert_eq!(distances.get(Coordinate::new(4, 4)), Some(&None));
+        assert_eq!(farthest(&distances), Some((Coordinate::new(3, 3), 4)));
+
+        let maze = parse("..F7.\n.FJ|.\nSJ.L7\n|F--J\nLJ...")?;
+        assert_eq!(
+            farthest(&maze.distances()),
+            Some((Coordinate::new(4, 2), 8))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn regions() -> Result<()> {
+        let input = "\
+            ..........\n\
+            .S------7.\n\
+            .|F----7|.\n\
+            .||OOOO||.\n\
+            .||OOOO||.\n\
+            .|L-7F-J|.\n\
+            .|II||II|.\n\
+            .L--JL--J.\n\
+            ..........";
+        let maze = parse(&input.replace(['O', 'I'], "."))?;
+        let regions = maze.regions();
+        for (line, row) in input.lines().zip(&regions.0) {
+            for (char, region) in line.chars().zip(row) {
+                let expected = match char {
+                    'I' => Region::Inside,
+                    'O' | '.' => Region::Outside,
+                    _ => Region::Loop,
+                };
+                assert_eq!(*region, expected);
+            }
+        }
+        assert_eq!(
+            maze.render_regions(&regions).lines().nth(6),
+            Some("O\u{2551}II\u{2551}\u{2551}II\u{2551}O")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn enclosed() -> Result<()> {
+        let mazes = [
+            ("-L|F7\n7S-7|\nL|7||\n-L-J|\nL|-JF", 1),
+            (
+                "...........\n.S-------7.\n.|F-----7|.\n.||.....||.\n.||.....||.\n\
+                .|L-7.F-J|.\n.|..|.|..|.\n.L--J.L--J.\n...........",
+                4,
+            ),
+            (
+                "..........\n.S------7.\n.|F----7|.\n.||OOOO||.\n.||OOOO||.\n\
+                .|L-7F-J|.\n.|II||II|.\n.L--JL--J.\n..........",
+                4,
+            ),
+            (
+                ".F----7F7F7F7F-7....\n.|F--7||||||||FJ....\n.||.FJ||||||||L7....\n\
+                FJL7L7LJLJ||LJ.L-7..\nL--J.L7...LJS7F-7L7.\n....F-J..F7FJ|L7L7L7\n\
+                ....L7.F7||L7|.L7L7|\n.....|FJLJ|FJ|F7|.LJ\n....FJL-7.||.||||...\n\
+                ....L---J.LJ.LJLJ...",
+                8,
+            ),
+            (
+                "FF7FSF7F7F7F7F7F---7\nL|LJ||||||||||||F--J\nFL-7LJLJ||||||LJL-77\n\
+                F--JF--7||LJLJ7F7FJ-\nL---JF-JLJ.||-FJLJJ7\n|F|F-JF---7F7-L7L|7|\n\
+                |FFJF7L7F-JF7|JL---7\n7-L-JL7||F7|L7F-7F7|\nL.L7LFJ|||||FJL7||LJ\n\
+                L7JLJL-JLJLJL--JLJ.L",
+                10,
+            ),
+        ];
+        for (input, enclosed) in mazes {
+            let maze = parse(&input.replace(['O', 'I'], "."))?;
+            let regions = maze.regions();
+            assert_eq!(count_enclosed(&regions), enclosed);
+            assert_eq!(maze.count_enclosed_flood(), enclosed);
+        }
+        Ok(())
+    }
+}
@@ -1,33 +1,184 @@
-use advent_of_code_2023::coordinates::Coordinate;
-use anyhow::{anyhow, Result};
-use itertools::Itertools;
+use advent_of_code_2023::{
+    coordinates::Coordinate,
+    solution::{Answer, Overflow},
+};
+use anyhow::{anyhow, Context, Result};
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+// Largest expanded map, in tiles, that will be rendered as text
+const MAX_RENDER_AREA: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
+enum Metric {
+    Manhattan,
+    Chebyshev,
+    EuclideanSquared,
+}
+
+impl Metric {
+    fn distance(self, from: Coordinate, to: Coordinate) -> Result<usize, Overflow> {
+        match self {
+            Self::Manhattan => from.manhatten_distance(to),
+            Self::Chebyshev => Some(from.chebyshev_distance(to)),
+            Self::EuclideanSquared => from.euclidean_distance_squared(to),
+        }
+        .ok_or(Overflow("distance between galaxies"))
+    }
+}
 
 struct Galaxies {
     galaxies: Vec<Coordinate>,
-    empty_columns: Vec<usize>,
-    empty_rows: Vec<usize>,
+    // Number of empty columns/rows before each index
+    empty_columns_before: Vec<usize>,
+    empty_rows_before: Vec<usize>,
 }
 
 impl Galaxies {
-    fn num_empty_cols_rows_between_coords(&self, from: Coordinate, to: Coordinate) -> usize {
-        (from.x.min(to.x)..=from.x.max(to.x))
-            .filter(|i| self.empty_columns.contains(i))
-            .count()
-            + (from.y.min(to.y)..=from.y.max(to.y))
-                .filter(|j| self.empty_rows.contains(j))
-                .count()
+    fn expanded_positions(
+        positions: impl Iterator<Item = usize>,
+        empty_before: &[usize],
+        multiplier: u128,
+    ) -> Option<Vec<u128>> {
+        positions
+            .map(|i| {
+                (empty_before[i] as u128)
+                    .checked_mul(multiplier.saturating_sub(1))?
+                    .checked_add(i as u128)
+            })
+            .collect()
     }
 
-    fn sum_of_pair_distances(&self, multiplier: usize) -> usize {
-        self.galaxies
+    // Sum of distances between all pairs along one axis. Once sorted, each position is
+    // the far end of a pair with every position before it.
+    fn sum_of_axis_distances(mut positions: Vec<u128>) -> Option<u128> {
+        positions.sort_unstable();
+        positions
             .iter()
-            .tuple_combinations()
-            .map(|(x, y)| {
-                x.manhatten_distance(*y)
-                    + self.num_empty_cols_rows_between_coords(*x, *y) * (multiplier - 1)
+            .enumerate()
+            .try_fold((0u128, 0u128), |(sum, prefix), (i, &position)| {
+                let distances = position.checked_mul(i as u128)?.checked_sub(prefix)?;
+                Some((sum.checked_add(distances)?, prefix.checked_add(position)?))
+            })
+            .map(|(sum, _)| sum)
+    }
+
+    fn expanded_columns_rows(&self, multiplier: u128) -> Option<(Vec<u128>, Vec<u128>)> {
+        let columns = Self::expanded_positions(
+            self.galaxies.iter().map(|coord| coord.x),
+            &self.empty_columns_before,
+            multiplier,
+        )?;
+        let rows = Self::expanded_positions(
+            self.galaxies.iter().map(|coord| coord.y),
+            &self.empty_rows_before,
+            multiplier,
+        )?;
+        Some((columns, rows))
+    }
+
+    fn expand(&self, multiplier: u128) -> Result<Vec<Coordinate>, Overflow> {
+        self.expanded_columns_rows(multiplier)
+            .and_then(|(columns, rows)| {
+                columns
+                    .into_iter()
+                    .zip(rows)
+                    .map(|(x, y)| Some(Coordinate::new(x.try_into().ok()?, y.try_into().ok()?)))
+                    .collect()
             })
-            .sum()
+            .ok_or(Overflow("expanded galaxy coordinates"))
+    }
+
+    fn sum_of_pair_distances(&self, multiplier: u128) -> Result<Answer, Overflow> {
+        self.expanded_columns_rows(multiplier)
+            .and_then(|(columns, rows)| {
+                Self::sum_of_axis_distances(columns)?
+                    .checked_add(Self::sum_of_axis_distances(rows)?)
+            })
+            .map(Answer::from)
+            .ok_or(Overflow("sum of galaxy pair distances"))
+    }
+}
+
+fn distances_from(
+    galaxies: &[Coordinate],
+    index: usize,
+    metric: Metric,
+) -> Result<Vec<(usize, usize)>, Overflow> {
+    let from = galaxies[index];
+    galaxies
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != index)
+        .map(|(i, to)| Ok((i, metric.distance(from, *to)?)))
+        .collect()
+}
+
+// Closest other galaxy as a pair of (index, distance)
+fn nearest(
+    galaxies: &[Coordinate],
+    index: usize,
+    metric: Metric,
+) -> Result<Option<(usize, usize)>, Overflow> {
+    Ok(distances_from(galaxies, index, metric)?
+        .into_iter()
+        .min_by_key(|(_, distance)| *distance))
+}
+
+// Farthest other galaxy as a pair of (index, distance)
+fn farthest(
+    galaxies: &[Coordinate],
+    index: usize,
+    metric: Metric,
+) -> Result<Option<(usize, usize)>, Overflow> {
+    Ok(distances_from(galaxies, index, metric)?
+        .into_iter()
+        .max_by_key(|(_, distance)| *distance))
+}
+
+fn distance_matrix(galaxies: &[Coordinate], metric: Metric) -> Result<Vec<Vec<usize>>, Overflow> {
+    galaxies
+        .iter()
+        .map(|from| {
+            galaxies
+                .iter()
+                .map(|to| metric.distance(*from, *to))
+                .collect()
+        })
+        .collect()
+}
+
+fn render(galaxies: &[Coordinate]) -> Option<String> {
+    let width = galaxies.iter().map(|coord| coord.x).max()? + 1;
+    let height = galaxies.iter().map(|coord| coord.y).max()? + 1;
+    if width.checked_mul(height)? > MAX_RENDER_AREA {
+        return None;
+    }
+
+    let mut map = vec![vec!['.'; width]; height];
+    for coord in galaxies {
+        map[coord.y][coord.x] = '#';
     }
+    Some(
+        map.into_iter()
+            .map(|row| row.into_iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+
+fn empty_before(occupied: impl Iterator<Item = usize>, len: usize) -> Vec<usize> {
+    let mut is_occupied = vec![false; len];
+    for i in occupied {
+        is_occupied[i] = true;
+    }
+    is_occupied
+        .into_iter()
+        .scan(0, |empty, occupied| {
+            let before = *empty;
+            *empty += usize::from(!occupied);
+            Some(before)
+        })
+        .collect()
 }
 
 fn parse(input: &str) -> Option<Galaxies> {
@@ -48,33 +199,175 @@ fn parse(input: &str) -> Option<Galaxies> {
     let width = galaxies.iter().map(|coord| coord.x).max()? + 1;
     let height = galaxies.iter().map(|coord| coord.y).max()? + 1;
 
-    let empty_columns: Vec<_> = (0..width)
-        .filter(|x| !galaxies.iter().any(|coord| coord.x == *x))
-        .collect();
-
-    let empty_rows: Vec<_> = (0..height)
-        .filter(|y| !galaxies.iter().any(|coord| coord.y == *y))
-        .collect();
+    let empty_columns_before = empty_before(galaxies.iter().map(|coord| coord.x), width);
+    let empty_rows_before = empty_before(galaxies.iter().map(|coord| coord.y), height);
 
     Some(Galaxies {
         galaxies,
-        empty_columns,
-        empty_rows,
+        empty_columns_before,
+        empty_rows_before,
     })
 }
 
+enum Command {
+    Answers,
+    Render { multiplier: u128 },
+    Distances { galaxy: usize, multiplier: u128 },
+}
+
+impl Command {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self> {
+        Ok(match args.next().as_deref() {
+            None => Self::Answers,
+            Some("render") => Self::Render {
+                multiplier: args
+                    .next()
+                    .map_or(Ok(2), |arg| arg.parse())
+                    .context("invalid expansion factor")?,
+            },
+            Some("distances") => Self::Distances {
+                galaxy: args
+                    .next()
+                    .map_or(Ok(1), |arg| arg.parse())
+                    .context("invalid galaxy number")?,
+                multiplier: args
+                    .next()
+                    .map_or(Ok(2), |arg| arg.parse())
+                    .context("invalid expansion factor")?,
+            },
+            Some(x) => return Err(anyhow!("unknown command {x}")),
+        })
+    }
+}
+
 fn main() -> Result<()> {
     let input = include_str!("../../input/day11.txt");
     let galaxies = parse(input).ok_or(anyhow!("failed to parse"))?;
 
+    match Command::from_args(std::env::args().skip(1))? {
+        Command::Answers => {}
+        Command::Render { multiplier } => {
+            let map = render(&galaxies.expand(multiplier)?)
+                .ok_or(anyhow!("expanded universe is too large to render"))?;
+            println!("{map}");
+            return Ok(());
+        }
+        Command::Distances { galaxy, multiplier } => {
+            let expanded = galaxies.expand(multiplier)?;
+            let index = galaxy
+                .checked_sub(1)
+                .filter(|&index| index < expanded.len())
+                .ok_or(anyhow!("no galaxy {galaxy}"))?;
+            for metric in Metric::iter() {
+                let matrix = distance_matrix(&expanded, metric)?;
+                let largest = matrix.iter().flatten().max().copied().unwrap_or(0);
+                println!("Largest {metric} distance between galaxies: {largest}");
+                if let (Some((nearest, near)), Some((farthest, far))) = (
+                    nearest(&expanded, index, metric)?,
+                    farthest(&expanded, index, metric)?,
+                ) {
+                    println!(
+                        "{metric} from galaxy {galaxy}: nearest {} ({near}), farthest {} ({far})",
+                        nearest + 1,
+                        farthest + 1
+                    );
+                }
+            }
+            return Ok(());
+        }
+    }
+
     println!(
         "Distance between galaxies with 2x expansion: {}",
-        galaxies.sum_of_pair_distances(2)
+        galaxies.sum_of_pair_distances(2)?
     );
     println!(
         "Distance between galaxies with 1,000,000x expansion: {}",
-        galaxies.sum_of_pair_distances(1_000_000)
+        galaxies.sum_of_pair_distances(1_000_000)?
     );
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{distance_matrix, farthest, nearest, parse, render, Metric};
+    use advent_of_code_2023::{coordinates::Coordinate, solution::Answer};
+    use anyhow::{anyhow, Result};
+
+    #[test]
+    fn pair_distances() -> Result<()> {
+        let input = include_str!("../../input/day11test.txt");
+        let galaxies = parse(input).ok_or(anyhow!("failed to parse"))?;
+
+        assert_eq!(galaxies.sum_of_pair_distances(2)?, Answer::Small(374));
+        assert_eq!(galaxies.sum_of_pair_distances(10)?, Answer::Small(1030));
+        assert_eq!(galaxies.sum_of_pair_distances(100)?, Answer::Small(8410));
+        assert!(galaxies.sum_of_pair_distances(u128::MAX).is_err());
+        assert!(galaxies.expand(u128::MAX).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn expand() -> Result<()> {
+        let input = include_str!("../../input/day11test.txt");
+        let galaxies = parse(input).ok_or(anyhow!("failed to parse"))?;
+
+        let expected = "\
+            ....#........\n\
+            .........#...\n\
+            #............\n\
+            .............\n\
+            .............\n\
+            ........#....\n\
+            .#...........\n\
+            ............#\n\
+            .............\n\
+            .............\n\
+            .........#...\n\
+            #....#.......";
+        assert_eq!(render(&galaxies.expand(2)?).as_deref(), Some(expected));
+        assert_eq!(galaxies.expand(1)?, galaxies.galaxies);
+        Ok(())
+    }
+
+    #[test]
+    fn distances() -> Result<()> {
+        let input = include_str!("../../input/day11test.txt");
+        let galaxies = parse(input).ok_or(anyhow!("failed to parse"))?.expand(2)?;
+
+        let matrix = distance_matrix(&galaxies, Metric::Manhattan)?;
+        assert_eq!(matrix[4][8], 9);
+        assert_eq!(matrix[0][6], 15);
+        assert_eq!(matrix[2][5], 17);
+        assert_eq!(matrix[7][8], 5);
+        assert_eq!(nearest(&galaxies, 7, Metric::Manhattan)?, Some((8, 5)));
+        assert_eq!(farthest(&galaxies, 7, Metric::Manhattan)?, Some((1, 19)));
+        // Galaxies 1 and 7 are 5 columns and 10 rows apart
+        assert_eq!(distance_matrix(&galaxies, Metric::Chebyshev)?[0][6], 10);
+        assert_eq!(
+            distance_matrix(&galaxies, Metric::EuclideanSquared)?[0][6],
+            125
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn distance_overflow() -> Result<()> {
+        let input = include_str!("../../input/day11test.txt");
+        let galaxies = parse(input).ok_or(anyhow!("failed to parse"))?;
+        let expanded = galaxies.expand(10_000_000_000)?;
+
+        // Squaring gaps of billions of tiles does not fit in a usize
+        assert!(distance_matrix(&expanded, Metric::EuclideanSquared).is_err());
+        assert!(nearest(&expanded, 0, Metric::EuclideanSquared).is_err());
+        assert!(farthest(&expanded, 0, Metric::EuclideanSquared).is_err());
+        assert!(distance_matrix(&expanded, Metric::Manhattan).is_ok());
+
+        let far = Coordinate::new(usize::MAX, usize::MAX);
+        let origin = Coordinate::new(0, 0);
+        assert!(Metric::Manhattan.distance(origin, far).is_err());
+        assert_eq!(Metric::Chebyshev.distance(origin, far)?, usize::MAX);
+        Ok(())
+    }
+}
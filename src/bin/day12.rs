@@ -1,8 +1,9 @@
-use anyhow::Result;
-use memoize::memoize;
+use advent_of_code_2023::solution::Answer;
+use anyhow::{anyhow, Context, Result};
+use num::{BigUint, One, Zero};
 use parse::parse;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use std::{fmt::Display, iter::repeat};
+use std::{collections::HashMap, fmt::Display, iter::repeat_n, str::FromStr};
 
 mod parse {
     use crate::{Row, Spring, Springs};
@@ -30,7 +31,7 @@ mod parse {
         let (springs, spring_groups) = separated_pair(
             springs,
             multispace1,
-            separated(1.., dec_uint::<_, u64, _>, ','),
+            separated(1.., dec_uint::<_, u64, _>.try_map(usize::try_from), ','),
         )
         .parse_next(input)?;
         Ok(Row {
@@ -44,6 +45,121 @@ mod parse {
     }
 }
 
+mod nonogram {
+    use crate::{Certainty, Row, Spring, Springs};
+    use advent_of_code_2023::coordinates::Grid;
+    use anyhow::{anyhow, Result};
+    use itertools::Itertools;
+    use winnow::{
+        ascii::{dec_uint, line_ending, multispace0},
+        combinator::{separated, terminated},
+        prelude::*,
+    };
+
+    #[derive(Debug)]
+    pub struct Nonogram {
+        rows: Vec<Vec<usize>>,
+        columns: Vec<Vec<usize>>,
+    }
+
+    impl Nonogram {
+        pub fn solve(&self) -> Option<Grid<Spring>> {
+            let grid = Grid(vec![
+                vec![Spring::Unknown; self.columns.len()];
+                self.rows.len()
+            ]);
+            self.backtrack(grid)
+        }
+
+        fn backtrack(&self, mut grid: Grid<Spring>) -> Option<Grid<Spring>> {
+            self.propagate(&mut grid)?;
+            let Some((y, x)) = grid.0.iter().enumerate().find_map(|(y, row)| {
+                row.iter()
+                    .position(|spring| *spring == Spring::Unknown)
+                    .map(|x| (y, x))
+            }) else {
+                return Some(grid);
+            };
+
+            [Spring::Damaged, Spring::Operational]
+                .into_iter()
+                .find_map(|guess| {
+                    let mut grid = grid.clone();
+                    grid.0[y][x] = guess;
+                    self.backtrack(grid)
+                })
+        }
+
+        // Line solve rows and columns until nothing changes, failing on contradictions
+        fn propagate(&self, grid: &mut Grid<Spring>) -> Option<()> {
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for (y, groups) in self.rows.iter().enumerate() {
+                    let line = solve_line(grid.0[y].clone(), groups)?;
+                    changed |= line != grid.0[y];
+                    grid.0[y] = line;
+                }
+                for (x, groups) in self.columns.iter().enumerate() {
+                    let column = grid.0.iter().map(|row| row[x]).collect_vec();
+                    let line = solve_line(column.clone(), groups)?;
+                    changed |= line != column;
+                    for (row, spring) in grid.0.iter_mut().zip(line) {
+                        row[x] = spring;
+                    }
+                }
+            }
+            Some(())
+        }
+    }
+
+    // Fix every unknown cell that is the same in all arrangements of the line
+    fn solve_line(springs: Vec<Spring>, groups: &[usize]) -> Option<Vec<Spring>> {
+        let mut row = Row {
+            springs: Springs(springs),
+            spring_groups: groups.to_vec(),
+        };
+        for cell in row.certainty()? {
+            row.springs.0[cell.position] = match cell.certainty {
+                Certainty::Damaged => Spring::Damaged,
+                Certainty::Operational => Spring::Operational,
+                Certainty::Either => Spring::Unknown,
+            };
+        }
+        Some(row.springs.0)
+    }
+
+    pub fn render(grid: &Grid<Spring>) -> String {
+        grid.0
+            .iter()
+            .map(|row| Springs(row.clone()).to_string())
+            .join("\n")
+    }
+
+    // A clue of 0 describes a line without any damaged springs
+    fn clue(input: &mut &str) -> PResult<Vec<usize>> {
+        separated(1.., dec_uint::<_, u64, _>.try_map(usize::try_from), ',')
+            .map(|groups: Vec<_>| groups.into_iter().filter(|&x| x > 0).collect())
+            .parse_next(input)
+    }
+
+    fn clues(input: &mut &str) -> PResult<Vec<Vec<usize>>> {
+        separated(1.., clue, line_ending).parse_next(input)
+    }
+
+    // Row clues and column clues, one per line, separated by an empty line
+    fn nonogram(input: &mut &str) -> PResult<Nonogram> {
+        let (rows, _, columns) =
+            terminated((clues, (line_ending, line_ending), clues), multispace0)
+                .parse_next(input)?;
+        Ok(Nonogram { rows, columns })
+    }
+
+    pub fn parse(input: &str) -> Result<Nonogram> {
+        nonogram.parse(input).map_err(|e| anyhow!(e.to_string()))
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Spring {
     Operational,
@@ -51,6 +167,19 @@ enum Spring {
     Unknown,
 }
 
+impl FromStr for Spring {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "." => Self::Operational,
+            "#" => Self::Damaged,
+            "?" => Self::Unknown,
+            _ => return Err(anyhow!("cannot convert {s} to spring")),
+        })
+    }
+}
+
 impl Display for Spring {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -65,7 +194,7 @@ impl Display for Spring {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Springs(Vec<Spring>);
 
 impl Display for Springs {
@@ -81,90 +210,438 @@ impl Display for Springs {
 #[derive(Debug, Clone)]
 struct Row {
     springs: Springs,
-    spring_groups: Vec<u64>,
-}
-
-#[memoize]
-fn recursive_check(
-    remaining: &'static [Spring],
-    to_place: &'static [u64],
-    num_placed: u64,
-    currently_in_a_row: u64,
-    current_group: usize,
-) -> u64 {
-    match to_place.get(current_group) {
-        None if currently_in_a_row > 0 => return 0,
-        Some(v) if *v < currently_in_a_row => return 0,
-        _ => {}
-    }
-
-    let operational = || {
-        recursive_check(
-            &remaining[1..],
-            to_place,
-            num_placed,
-            0,
-            current_group + usize::from(currently_in_a_row > 0),
+    spring_groups: Vec<usize>,
+}
+
+impl Row {
+    fn unfold(&self, amount: usize, joiner: Spring) -> Self {
+        Self {
+            springs: Springs(
+                repeat_n(self.springs.0.clone(), amount)
+                    .collect::<Vec<_>>()
+                    .join(&joiner),
+            ),
+            spring_groups: self.spring_groups.repeat(amount),
+        }
+    }
+
+    fn num_arrangements(&self) -> BigUint {
+        ArrangementTable::new(self).num_arrangements()
+    }
+
+    // Composes the outcomes of a single copy instead of counting over the unfolded row
+    fn num_unfolded_arrangements(&self, amount: usize, joiner: Spring) -> BigUint {
+        let groups = &self.spring_groups;
+        if groups.is_empty() || amount <= 1 {
+            return self.unfold(amount, joiner).num_arrangements();
+        }
+        let joined = CopyTransfer::new(&[self.springs.0.as_slice(), &[joiner]].concat(), groups);
+        let last = CopyTransfer::new(&self.springs.0, groups);
+        let total_groups = groups.len() * amount;
+        let size = |group: usize| (group < total_groups).then(|| groups[group % groups.len()]);
+
+        let mut reached = HashMap::from([((0, 0), BigUint::one())]);
+        for copy in 1..=amount {
+            let transfer = if copy == amount { &last } else { &joined };
+            let mut next = HashMap::new();
+            for ((group, run), count) in reached {
+                for (completed, run, ways) in &transfer.0[group % groups.len()][run] {
+                    let group = group + completed;
+                    if group < total_groups || (group == total_groups && *run == 0) {
+                        *next.entry((group, *run)).or_insert_with(BigUint::zero) += &count * ways;
+                    }
+                }
+            }
+            reached = next;
+        }
+
+        reached
+            .into_iter()
+            .filter(|((group, run), _)| {
+                ArrangementTable::next_state(size(*group), *group, *run, Spring::Operational)
+                    == Some((total_groups, 0))
+            })
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    // Arrangements are ordered lexicographically by their text representation
+    fn arrangements(&self) -> Arrangements<'_> {
+        Arrangements {
+            table: ArrangementTable::new(self),
+            next: BigUint::zero(),
+        }
+    }
+
+    fn nth_arrangement(&self, n: &BigUint) -> Option<Springs> {
+        ArrangementTable::new(self).nth_arrangement(n)
+    }
+
+    // Report on every unknown spring, or None if the row has no arrangements
+    fn certainty(&self) -> Option<Vec<CellCertainty>> {
+        let table = ArrangementTable::new(self);
+        let total = table.num_arrangements();
+        if total.is_zero() {
+            return None;
+        }
+        Some(
+            self.springs
+                .0
+                .iter()
+                .zip(table.damaged_counts())
+                .enumerate()
+                .filter(|(_, (spring, _))| **spring == Spring::Unknown)
+                .map(|(position, (_, damaged_in))| CellCertainty {
+                    position,
+                    certainty: if damaged_in.is_zero() {
+                        Certainty::Operational
+                    } else if damaged_in == total {
+                        Certainty::Damaged
+                    } else {
+                        Certainty::Either
+                    },
+                    damaged_in,
+                })
+                .collect(),
         )
-    };
-    let damaged = || {
-        recursive_check(
-            &remaining[1..],
-            to_place,
-            num_placed + 1,
-            currently_in_a_row + 1,
-            current_group,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Certainty {
+    Damaged,
+    Operational,
+    Either,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CellCertainty {
+    position: usize,
+    certainty: Certainty,
+    damaged_in: BigUint,
+}
+
+struct Arrangements<'a> {
+    table: ArrangementTable<'a>,
+    next: BigUint,
+}
+
+impl Iterator for Arrangements<'_> {
+    type Item = Springs;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let arrangement = self.table.nth_arrangement(&self.next)?;
+        self.next += 1u32;
+        Some(arrangement)
+    }
+}
+
+// Outcomes of one copy of a row, for every state it can be entered in, indexed by the
+// position in the repeating groups and the length of the current damaged run. Each
+// outcome is the number of groups completed in the copy, the run length after it and
+// the number of ways to get there.
+struct CopyTransfer(Vec<Vec<Vec<(usize, usize, BigUint)>>>);
+
+impl CopyTransfer {
+    fn new(springs: &[Spring], groups: &[usize]) -> Self {
+        let max_run = groups.iter().max().copied().unwrap_or(0);
+        Self(
+            (0..groups.len())
+                .map(|start| {
+                    (0..=max_run)
+                        .map(|run| Self::outcomes(springs, groups, start, run))
+                        .collect()
+                })
+                .collect(),
         )
-    };
+    }
 
-    match remaining.first() {
-        None => (num_placed == to_place.iter().sum()).into(),
-        Some(Spring::Operational) => operational(),
-        Some(Spring::Damaged) => damaged(),
-        Some(Spring::Unknown) => damaged() + operational(),
+    fn outcomes(
+        springs: &[Spring],
+        groups: &[usize],
+        start: usize,
+        run: usize,
+    ) -> Vec<(usize, usize, BigUint)> {
+        let mut reached = HashMap::from([((0, run), BigUint::one())]);
+        for spring in springs {
+            let mut next = HashMap::new();
+            for ((completed, run), count) in reached {
+                let size = groups[(start + completed) % groups.len()];
+                for spring in ArrangementTable::possible_springs(*spring) {
+                    if let Some(state) =
+                        ArrangementTable::next_state(Some(size), completed, run, *spring)
+                    {
+                        *next.entry(state).or_insert_with(BigUint::zero) += &count;
+                    }
+                }
+            }
+            reached = next;
+        }
+        reached
+            .into_iter()
+            .map(|((completed, run), count)| (completed, run, count))
+            .collect()
     }
 }
 
-fn repeat_and_leak(row: &Row, amount: usize) -> (&'static [Spring], &'static [u64]) {
-    let springs = repeat(row.springs.0.iter().copied().chain([Spring::Unknown]))
-        .take(amount)
-        .flatten()
-        .take(row.springs.0.len() * amount + (amount - 1))
-        .collect::<Vec<_>>()
-        .leak();
-    let spring_grouos = repeat(row.spring_groups.iter().copied())
-        .take(amount)
-        .flatten()
-        .collect::<Vec<_>>()
-        .leak();
-    (springs, spring_grouos)
+// Number of ways to finish a row from every state, indexed by position in the springs,
+// number of completed groups and length of the damaged run currently being placed
+struct ArrangementTable<'a> {
+    row: &'a Row,
+    ways: Vec<Vec<Vec<BigUint>>>,
+}
+
+impl<'a> ArrangementTable<'a> {
+    fn new(row: &'a Row) -> Self {
+        let groups = &row.spring_groups;
+        let max_run = groups.iter().max().copied().unwrap_or(0);
+        let empty = vec![vec![BigUint::zero(); max_run + 1]; groups.len() + 1];
+
+        let mut last = empty.clone();
+        for (group, runs) in last.iter_mut().enumerate() {
+            for (run, ways) in runs.iter_mut().enumerate() {
+                if Self::next_state(groups.get(group).copied(), group, run, Spring::Operational)
+                    == Some((groups.len(), 0))
+                {
+                    *ways = BigUint::one();
+                }
+            }
+        }
+
+        let mut ways = vec![last];
+        for spring in row.springs.0.iter().rev() {
+            let next = ways.last().unwrap();
+            let mut current = empty.clone();
+            for (group, runs) in current.iter_mut().enumerate() {
+                for (run, count) in runs.iter_mut().enumerate() {
+                    *count = Self::possible_springs(*spring)
+                        .iter()
+                        .filter_map(|spring| {
+                            Self::next_state(groups.get(group).copied(), group, run, *spring)
+                        })
+                        .map(|(group, run)| &next[group][run])
+                        .sum();
+                }
+            }
+            ways.push(current);
+        }
+        ways.reverse();
+
+        Self { row, ways }
+    }
+
+    fn possible_springs(spring: Spring) -> &'static [Spring] {
+        match spring {
+            Spring::Operational => &[Spring::Operational],
+            Spring::Damaged => &[Spring::Damaged],
+            Spring::Unknown => &[Spring::Damaged, Spring::Operational],
+        }
+    }
+
+    // Size is that of the group currently being placed, or None once all are placed
+    fn next_state(
+        size: Option<usize>,
+        group: usize,
+        run: usize,
+        spring: Spring,
+    ) -> Option<(usize, usize)> {
+        match spring {
+            Spring::Operational if run == 0 => Some((group, 0)),
+            Spring::Operational if size == Some(run) => Some((group + 1, 0)),
+            Spring::Damaged if size.is_some_and(|size| run < size) => Some((group, run + 1)),
+            _ => None,
+        }
+    }
+
+    fn num_arrangements(&self) -> BigUint {
+        self.ways[0][0][0].clone()
+    }
+
+    // Number of arrangements in which each spring is damaged
+    fn damaged_counts(&self) -> Vec<BigUint> {
+        let groups = &self.row.spring_groups;
+        let mut reached = vec![vec![BigUint::zero(); self.ways[0][0].len()]; groups.len() + 1];
+        reached[0][0] = BigUint::one();
+
+        let mut counts = Vec::with_capacity(self.row.springs.0.len());
+        for (i, spring) in self.row.springs.0.iter().enumerate() {
+            let mut next = vec![vec![BigUint::zero(); reached[0].len()]; reached.len()];
+            let mut damaged = BigUint::zero();
+            for (group, runs) in reached.iter().enumerate() {
+                for (run, count) in runs.iter().enumerate().filter(|(_, x)| !x.is_zero()) {
+                    for spring in Self::possible_springs(*spring) {
+                        let Some((group, run)) =
+                            Self::next_state(groups.get(group).copied(), group, run, *spring)
+                        else {
+                            continue;
+                        };
+                        if *spring == Spring::Damaged {
+                            damaged += count * &self.ways[i + 1][group][run];
+                        }
+                        next[group][run] += count;
+                    }
+                }
+            }
+            counts.push(damaged);
+            reached = next;
+        }
+        counts
+    }
+
+    // Walks the table, skipping past every choice with fewer remaining arrangements than n
+    fn nth_arrangement(&self, n: &BigUint) -> Option<Springs> {
+        let mut n = n.clone();
+        let (mut group, mut run) = (0, 0);
+        let mut springs = Vec::with_capacity(self.row.springs.0.len());
+        for (i, spring) in self.row.springs.0.iter().enumerate() {
+            let (chosen, next) = Self::possible_springs(*spring).iter().find_map(|spring| {
+                let (group, run) = Self::next_state(
+                    self.row.spring_groups.get(group).copied(),
+                    group,
+                    run,
+                    *spring,
+                )?;
+                let ways = &self.ways[i + 1][group][run];
+                if n < *ways {
+                    Some((*spring, (group, run)))
+                } else {
+                    n -= ways;
+                    None
+                }
+            })?;
+            springs.push(chosen);
+            (group, run) = next;
+        }
+        Some(Springs(springs))
+    }
 }
 
-fn num_arrangements(rows: &[Row], amount: usize) -> u64 {
+fn num_arrangements(rows: &[Row], amount: usize, joiner: Spring) -> Answer {
     rows.par_iter()
-        .map(|row| {
-            let (springs, spring_groups) = repeat_and_leak(row, amount);
-            recursive_check(springs, spring_groups, 0, 0, 0)
+        .map(|row| row.num_unfolded_arrangements(amount, joiner))
+        .sum::<BigUint>()
+        .into()
+}
+
+enum Command {
+    Solve { amount: usize, joiner: Spring },
+    Arrangements { line: usize },
+    NthArrangement { line: usize, n: BigUint },
+    Certainty { line: usize },
+    Nonogram { path: String },
+}
+
+impl Command {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self> {
+        Ok(match args.next().as_deref() {
+            None => Self::Solve {
+                amount: 5,
+                joiner: Spring::Unknown,
+            },
+            Some("unfold") => Self::Solve {
+                amount: next_arg(&mut args, "unfold factor")?,
+                joiner: match args.next() {
+                    Some(joiner) => joiner.parse().context("invalid joiner")?,
+                    None => Spring::Unknown,
+                },
+            },
+            Some("arrangements") => Self::Arrangements {
+                line: next_arg(&mut args, "line number")?,
+            },
+            Some("nth") => Self::NthArrangement {
+                line: next_arg(&mut args, "line number")?,
+                n: next_arg(&mut args, "arrangement index")?,
+            },
+            Some("certainty") => Self::Certainty {
+                line: next_arg(&mut args, "line number")?,
+            },
+            Some("nonogram") => Self::Nonogram {
+                path: next_arg(&mut args, "nonogram path")?,
+            },
+            Some(x) => return Err(anyhow!("unknown command {x}")),
         })
-        .sum()
+    }
+}
+
+fn next_arg<T>(args: &mut impl Iterator<Item = String>, name: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    args.next()
+        .with_context(|| format!("missing {name}"))?
+        .parse()
+        .with_context(|| format!("invalid {name}"))
+}
+
+fn get_row(rows: &[Row], line: usize) -> Result<&Row> {
+    line.checked_sub(1)
+        .and_then(|i| rows.get(i))
+        .ok_or(anyhow!("no row on line {line}"))
 }
 
 fn main() -> Result<()> {
     let input = include_str!("../../input/day12.txt");
     let rows = input.lines().map(parse).collect::<Result<Vec<_>>>()?;
 
-    let arrangements = num_arrangements(&rows, 1);
+    let (amount, joiner) = match Command::from_args(std::env::args().skip(1))? {
+        Command::Solve { amount, joiner } => (amount, joiner),
+        Command::Arrangements { line } => {
+            for arrangement in get_row(&rows, line)?.arrangements() {
+                println!("{arrangement}");
+            }
+            return Ok(());
+        }
+        Command::NthArrangement { line, n } => {
+            let arrangement = get_row(&rows, line)?
+                .nth_arrangement(&n)
+                .ok_or(anyhow!("row on line {line} has no arrangement {n}"))?;
+            println!("{arrangement}");
+            return Ok(());
+        }
+        Command::Certainty { line } => {
+            let row = get_row(&rows, line)?;
+            let total = row.num_arrangements();
+            let cells = row
+                .certainty()
+                .ok_or(anyhow!("row on line {line} has no arrangements"))?;
+            println!("{} ({total} arrangements)", row.springs);
+            for cell in cells {
+                println!(
+                    "{:>4}: {:?}, damaged in {} of {total}",
+                    cell.position + 1,
+                    cell.certainty,
+                    cell.damaged_in
+                );
+            }
+            return Ok(());
+        }
+        Command::Nonogram { path } => {
+            let nonogram = nonogram::parse(&std::fs::read_to_string(&path)?)?;
+            let solution = nonogram
+                .solve()
+                .ok_or(anyhow!("nonogram in {path} has no solution"))?;
+            println!("{}", nonogram::render(&solution));
+            return Ok(());
+        }
+    };
+
+    let arrangements = num_arrangements(&rows, 1, joiner);
     println!("Number of possible arrangements: {arrangements}");
 
-    let arrangements_x5 = num_arrangements(&rows, 5);
-    println!("Number of possible arrangements for input of 5x size: {arrangements_x5}");
+    let arrangements_unfolded = num_arrangements(&rows, amount, joiner);
+    println!(
+        "Number of possible arrangements for input of {amount}x size joined by {joiner}: \
+        {arrangements_unfolded}"
+    );
     Ok(())
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{parse, recursive_check, repeat_and_leak, Row};
+    use crate::{nonogram, parse, Certainty, Row, Spring};
     use anyhow::Result;
+    use num::BigUint;
 
     fn read_data() -> Result<Vec<Row>> {
         let input = include_str!("../../input/day12test.txt");
@@ -172,50 +649,140 @@ mod test {
     }
 
     #[test]
-    fn recursive() -> Result<()> {
+    fn arrangements() -> Result<()> {
         let rows = read_data()?;
 
-        let (springs, spring_groups) = repeat_and_leak(&rows[0], 1);
-        assert_eq!(recursive_check(springs, spring_groups, 0, 0, 0), 1);
-
-        let (springs, spring_groups) = repeat_and_leak(&rows[1], 1);
-        assert_eq!(recursive_check(springs, spring_groups, 0, 0, 0), 4);
-
-        let (springs, spring_groups) = repeat_and_leak(&rows[2], 1);
-        assert_eq!(recursive_check(springs, spring_groups, 0, 0, 0), 1);
-
-        let (springs, spring_groups) = repeat_and_leak(&rows[3], 1);
-        assert_eq!(recursive_check(springs, spring_groups, 0, 0, 0), 1);
+        assert_eq!(
+            rows[0].unfold(1, Spring::Unknown).num_arrangements(),
+            BigUint::from(1u32)
+        );
+        assert_eq!(
+            rows[1].unfold(1, Spring::Unknown).num_arrangements(),
+            BigUint::from(4u32)
+        );
+        assert_eq!(
+            rows[2].unfold(1, Spring::Unknown).num_arrangements(),
+            BigUint::from(1u32)
+        );
+        assert_eq!(
+            rows[3].unfold(1, Spring::Unknown).num_arrangements(),
+            BigUint::from(1u32)
+        );
+        assert_eq!(
+            rows[4].unfold(1, Spring::Unknown).num_arrangements(),
+            BigUint::from(4u32)
+        );
+        assert_eq!(
+            rows[5].unfold(1, Spring::Unknown).num_arrangements(),
+            BigUint::from(10u32)
+        );
+        Ok(())
+    }
 
-        let (springs, spring_groups) = repeat_and_leak(&rows[4], 1);
-        assert_eq!(recursive_check(springs, spring_groups, 0, 0, 0), 4);
+    #[test]
+    fn arrangements_unfolded() -> Result<()> {
+        let rows = read_data()?;
 
-        let (springs, spring_groups) = repeat_and_leak(&rows[5], 1);
-        assert_eq!(recursive_check(springs, spring_groups, 0, 0, 0), 10);
+        assert_eq!(
+            rows[0].unfold(5, Spring::Unknown).num_arrangements(),
+            BigUint::from(1u32)
+        );
+        assert_eq!(
+            rows[1].unfold(5, Spring::Unknown).num_arrangements(),
+            BigUint::from(16384u32)
+        );
+        assert_eq!(
+            rows[2].unfold(5, Spring::Unknown).num_arrangements(),
+            BigUint::from(1u32)
+        );
+        assert_eq!(
+            rows[3].unfold(5, Spring::Unknown).num_arrangements(),
+            BigUint::from(16u32)
+        );
+        assert_eq!(
+            rows[4].unfold(5, Spring::Unknown).num_arrangements(),
+            BigUint::from(2500u32)
+        );
+        assert_eq!(
+            rows[5].unfold(5, Spring::Unknown).num_arrangements(),
+            BigUint::from(506_250u32)
+        );
         Ok(())
     }
 
     #[test]
-    fn recursive2() -> Result<()> {
+    fn transfer() -> Result<()> {
         let rows = read_data()?;
 
-        let (springs, spring_groups) = repeat_and_leak(&rows[0], 5);
-        assert_eq!(recursive_check(springs, spring_groups, 0, 0, 0), 1);
+        for row in rows {
+            for joiner in [Spring::Unknown, Spring::Operational, Spring::Damaged] {
+                for amount in 1..=6 {
+                    assert_eq!(
+                        row.num_unfolded_arrangements(amount, joiner),
+                        row.unfold(amount, joiner).num_arrangements()
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
 
-        let (springs, spring_groups) = repeat_and_leak(&rows[1], 5);
-        assert_eq!(recursive_check(springs, spring_groups, 0, 0, 0), 16384);
+    #[test]
+    fn enumerate_arrangements() -> Result<()> {
+        let rows = read_data()?;
 
-        let (springs, spring_groups) = repeat_and_leak(&rows[2], 5);
-        assert_eq!(recursive_check(springs, spring_groups, 0, 0, 0), 1);
+        for row in rows {
+            let arrangements: Vec<_> = row.arrangements().collect();
+            assert_eq!(BigUint::from(arrangements.len()), row.num_arrangements());
+            assert!(arrangements
+                .windows(2)
+                .all(|pair| pair[0].to_string() < pair[1].to_string()));
+            for (i, springs) in arrangements.into_iter().enumerate() {
+                assert!(!springs.0.contains(&Spring::Unknown));
+                assert_eq!(row.nth_arrangement(&i.into()), Some(springs.clone()));
+                let solved = Row {
+                    springs,
+                    spring_groups: row.spring_groups.clone(),
+                };
+                assert_eq!(solved.num_arrangements(), BigUint::from(1u32));
+            }
+        }
+        Ok(())
+    }
 
-        let (springs, spring_groups) = repeat_and_leak(&rows[3], 5);
-        assert_eq!(recursive_check(springs, spring_groups, 0, 0, 0), 16);
+    #[test]
+    fn certainty() -> Result<()> {
+        let rows = read_data()?;
 
-        let (springs, spring_groups) = repeat_and_leak(&rows[4], 5);
-        assert_eq!(recursive_check(springs, spring_groups, 0, 0, 0), 2500);
+        let cells = rows[5].certainty().unwrap();
+        assert_eq!(cells.len(), 9);
+        assert_eq!(cells[0].certainty, Certainty::Operational);
+        assert_eq!(cells[1].certainty, Certainty::Operational);
+        assert!(cells[2..]
+            .iter()
+            .all(|cell| cell.certainty == Certainty::Either));
+        for cell in cells {
+            let damaged = rows[5]
+                .arrangements()
+                .filter(|springs| springs.0[cell.position] == Spring::Damaged)
+                .count();
+            assert_eq!(cell.damaged_in, BigUint::from(damaged));
+        }
+        Ok(())
+    }
 
-        let (springs, spring_groups) = repeat_and_leak(&rows[5], 5);
-        assert_eq!(recursive_check(springs, spring_groups, 0, 0, 0), 506_250);
+    #[test]
+    fn solve_nonogram() -> Result<()> {
+        let puzzle = nonogram::parse("1,1\n5\n1,1\n3\n0\n\n1\n4\n1,1\n4\n1")?;
+        let solution = puzzle.solve().map(|grid| nonogram::render(&grid));
+        assert_eq!(
+            solution.as_deref(),
+            Some(".#.#.\n#####\n.#.#.\n.###.\n.....")
+        );
+
+        let ambiguous = nonogram::parse("1\n1\n\n1\n1")?;
+        let solution = ambiguous.solve().map(|grid| nonogram::render(&grid));
+        assert_eq!(solution.as_deref(), Some("#.\n.#"));
         Ok(())
     }
 }
@@ -1,8 +1,11 @@
-use advent_of_code_2023::coordinates::Grid;
-use anyhow::Result;
+use advent_of_code_2023::{
+    coordinates::{Coordinate, Grid},
+    random::XorShift,
+};
+use anyhow::{anyhow, Result};
 use itertools::Itertools;
 use parse::parse;
-use std::fmt::Display;
+use std::{fmt::Display, io::IsTerminal, time::Instant};
 
 mod parse {
     use crate::{Floor, Tile};
@@ -53,6 +56,30 @@ impl Display for Tile {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+// Line of reflection, placed after the given number of rows or columns, along with the
+// mirrored pairs of cells that differ
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Reflection {
+    axis: Axis,
+    index: usize,
+    smudges: Vec<[Coordinate; 2]>,
+}
+
+impl Reflection {
+    fn value(&self) -> usize {
+        match self.axis {
+            Axis::Horizontal => self.index * 100,
+            Axis::Vertical => self.index,
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Floor(Grid<Tile>);
 
@@ -65,38 +92,295 @@ impl Floor {
         ))
     }
 
-    fn mirror_value(&self, smudged: bool) -> usize {
-        self.horizontal_mirror_rows(smudged)
-            .map(|x| x * 100)
-            .or(self.rotate().horizontal_mirror_rows(smudged))
-            .expect("Failed to find any mirrored planes")
-    }
-
-    fn horizontal_mirror_rows(&self, smudged: bool) -> Option<usize> {
-        (1..self.0 .0.len()).find(|&i| {
-            let mut lower_index = i;
-            let mut upper_index = i - 1;
-            let mut smudged = !smudged;
-            loop {
-                let (Some(upper), Some(lower)) =
-                    (self.0 .0.get(upper_index), self.0 .0.get(lower_index))
-                else {
-                    return smudged;
-                };
-
-                match upper.iter().zip(lower).filter(|(x, y)| x != y).count() {
-                    0 => {}
-                    1 if !smudged => smudged = true,
-                    _ => return false,
+    fn pack(&self) -> PackedFloor {
+        let height = self.0 .0.len();
+        let width = self.0 .0.first().map_or(0, Vec::len);
+        let mut rows = vec![Bitset::new(width); height];
+        let mut columns = vec![Bitset::new(height); width];
+        for (y, row) in self.0 .0.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                if *tile == Tile::Rocks {
+                    rows[y].set(x);
+                    columns[x].set(y);
+                }
+            }
+        }
+        PackedFloor { rows, columns }
+    }
+
+    fn reflections(&self, smudges: usize) -> Vec<Reflection> {
+        self.pack().reflections(smudges)
+    }
+
+    fn reflections_by_cell(&self, smudges: usize) -> Vec<Reflection> {
+        let transpose =
+            |[x, y]: [Coordinate; 2]| [Coordinate::new(x.y, x.x), Coordinate::new(y.y, y.x)];
+        self.horizontal_mirror_rows(smudges)
+            .map(|(index, smudges)| Reflection {
+                axis: Axis::Horizontal,
+                index,
+                smudges,
+            })
+            .chain(
+                self.rotate()
+                    .horizontal_mirror_rows(smudges)
+                    .map(|(index, smudges)| Reflection {
+                        axis: Axis::Vertical,
+                        index,
+                        smudges: smudges.into_iter().map(transpose).collect(),
+                    }),
+            )
+            .collect()
+    }
+
+    fn reflection(&self, smudges: usize) -> Result<Reflection> {
+        self.reflections(smudges)
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("failed to find any mirrored planes in pattern\n{self}"))
+    }
+
+    // Lines where exactly the given number of mirrored cells differ
+    fn horizontal_mirror_rows(
+        &self,
+        smudges: usize,
+    ) -> impl Iterator<Item = (usize, Vec<[Coordinate; 2]>)> + '_ {
+        let rows = &self.0 .0;
+        (1..rows.len()).filter_map(move |i| {
+            let mut found = vec![];
+            for (upper_index, lower_index) in (0..i).rev().zip(i..rows.len()) {
+                let upper = &rows[upper_index];
+                let lower = &rows[lower_index];
+                for (x, _) in upper
+                    .iter()
+                    .zip(lower)
+                    .enumerate()
+                    .filter(|(_, (x, y))| x != y)
+                {
+                    if found.len() == smudges {
+                        return None;
+                    }
+                    found.push([
+                        Coordinate::new(x, upper_index),
+                        Coordinate::new(x, lower_index),
+                    ]);
                 }
+            }
+            (found.len() == smudges).then_some((i, found))
+        })
+    }
+
+    // Draws the pattern numbered and with the line of reflection marked in the margins as
+    // in the puzzle text. Smudged cells are coloured, or drawn as '*' without colour.
+    fn render(&self, reflection: &Reflection, colour: bool) -> String {
+        let rows = &self.0 .0;
+        let width = rows.first().map_or(0, Vec::len);
+        let smudged: Vec<_> = reflection.smudges.iter().flatten().copied().collect();
+        let label = |i: usize| char::from_digit(((i + 1) % 10) as u32, 10).unwrap();
+        let marker = |i: usize, before: char, after: char| {
+            if i + 1 == reflection.index {
+                before
+            } else if i == reflection.index {
+                after
+            } else {
+                ' '
+            }
+        };
+        let row = |y: usize| {
+            rows[y]
+                .iter()
+                .enumerate()
+                .map(|(x, tile)| {
+                    if !smudged.contains(&Coordinate::new(x, y)) {
+                        tile.to_string()
+                    } else if colour {
+                        format!("\x1b[1;31m{tile}\x1b[0m")
+                    } else {
+                        "*".to_owned()
+                    }
+                })
+                .collect::<String>()
+        };
+
+        match reflection.axis {
+            Axis::Horizontal => (0..rows.len())
+                .map(|y| {
+                    let marker = marker(y, 'v', '^');
+                    format!("{}{marker}{}{marker}{}", label(y), row(y), label(y))
+                })
+                .join("\n"),
+            Axis::Vertical => {
+                let labels: String = (0..width).map(label).collect();
+                let markers: String = (0..width).map(|x| marker(x, '>', '<')).collect();
+                [labels.clone(), markers.clone()]
+                    .into_iter()
+                    .chain((0..rows.len()).map(row))
+                    .chain([markers, labels])
+                    .join("\n")
+            }
+        }
+    }
+}
+
+// Tiles of a row or column packed into words, with rocks as set bits
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Bitset(Vec<u64>);
+
+impl Bitset {
+    fn new(len: usize) -> Self {
+        Self(vec![0; len.div_ceil(64)])
+    }
+
+    fn set(&mut self, i: usize) {
+        self.0[i / 64] |= 1 << (i % 64);
+    }
+
+    fn count_differences(&self, other: &Self) -> usize {
+        self.0
+            .iter()
+            .zip(&other.0)
+            .map(|(x, y)| (x ^ y).count_ones() as usize)
+            .sum()
+    }
+
+    fn differences<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = usize> + 'a {
+        self.0
+            .iter()
+            .zip(&other.0)
+            .enumerate()
+            .flat_map(|(i, (x, y))| {
+                let mut word = x ^ y;
+                std::iter::from_fn(move || {
+                    (word != 0).then(|| {
+                        let bit = word.trailing_zeros() as usize;
+                        word &= word - 1;
+                        i * 64 + bit
+                    })
+                })
+            })
+    }
+}
+
+#[derive(Debug)]
+struct PackedFloor {
+    rows: Vec<Bitset>,
+    columns: Vec<Bitset>,
+}
+
+impl PackedFloor {
+    fn reflections(&self, smudges: usize) -> Vec<Reflection> {
+        Self::mirror_lines(&self.rows, smudges)
+            .map(|(index, differences)| Reflection {
+                axis: Axis::Horizontal,
+                index,
+                smudges: differences
+                    .into_iter()
+                    .map(|(x, upper, lower)| [Coordinate::new(x, upper), Coordinate::new(x, lower)])
+                    .collect(),
+            })
+            .chain(
+                Self::mirror_lines(&self.columns, smudges).map(|(index, differences)| Reflection {
+                    axis: Axis::Vertical,
+                    index,
+                    smudges: differences
+                        .into_iter()
+                        .map(|(y, left, right)| {
+                            [Coordinate::new(left, y), Coordinate::new(right, y)]
+                        })
+                        .collect(),
+                }),
+            )
+            .collect()
+    }
 
-                upper_index = match upper_index.checked_sub(1) {
-                    Some(i) => i,
-                    None => return smudged,
-                };
-                lower_index += 1;
+    // Lines where exactly the given number of mirrored cells differ, along with the
+    // position of each difference and the pair of lines it is found in
+    fn mirror_lines(
+        lines: &[Bitset],
+        smudges: usize,
+    ) -> impl Iterator<Item = (usize, Vec<(usize, usize, usize)>)> + '_ {
+        (1..lines.len()).filter_map(move |i| {
+            let pairs = (0..i).rev().zip(i..lines.len());
+            let mut count = 0;
+            for (upper, lower) in pairs.clone() {
+                count += lines[upper].count_differences(&lines[lower]);
+                if count > smudges {
+                    return None;
+                }
             }
+            (count == smudges).then(|| {
+                let differences = pairs
+                    .flat_map(|(upper, lower)| {
+                        lines[upper]
+                            .differences(&lines[lower])
+                            .map(move |position| (position, upper, lower))
+                    })
+                    .collect();
+                (i, differences)
+            })
+        })
+    }
+}
+
+// Pattern mirrored around its middle row, with the given number of cells flipped
+fn synthetic_floor(width: usize, height: usize, flipped: usize, seed: u64) -> Floor {
+    let mut random = XorShift::new(seed);
+    let half: Vec<Vec<_>> = (0..height / 2)
+        .map(|_| {
+            (0..width)
+                .map(|_| {
+                    if random.below(2) == 0 {
+                        Tile::Ash
+                    } else {
+                        Tile::Rocks
+                    }
+                })
+                .collect()
         })
+        .collect();
+    let mut rows: Vec<_> = half.iter().chain(half.iter().rev()).cloned().collect();
+    for _ in 0..flipped {
+        let y = random.below(rows.len());
+        let x = random.below(width);
+        rows[y][x] = match rows[y][x] {
+            Tile::Ash => Tile::Rocks,
+            Tile::Rocks => Tile::Ash,
+        };
+    }
+    Floor(Grid(rows))
+}
+
+fn bench(floors: &[Floor], iterations: usize) {
+    let synthetic: Vec<_> = (0..10)
+        .map(|seed| synthetic_floor(200, 200, seed as usize % 3, seed))
+        .collect();
+    for (name, floors) in [("input", floors), ("synthetic 200x200", &synthetic)] {
+        let start = Instant::now();
+        for _ in 0..iterations {
+            for floor in floors {
+                std::hint::black_box(floor.reflections_by_cell(0));
+                std::hint::black_box(floor.reflections_by_cell(1));
+            }
+        }
+        let by_cell = start.elapsed();
+
+        let start = Instant::now();
+        let packed: Vec<_> = floors.iter().map(Floor::pack).collect();
+        let packing = start.elapsed();
+        let start = Instant::now();
+        for _ in 0..iterations {
+            for floor in &packed {
+                std::hint::black_box(floor.reflections(0));
+                std::hint::black_box(floor.reflections(1));
+            }
+        }
+        let bitset = start.elapsed();
+
+        println!(
+            "{name}: cell by cell {:?}, bitset {:?} per iteration, packing {packing:?}",
+            by_cell / iterations as u32,
+            bitset / iterations as u32
+        );
     }
 }
 
@@ -118,10 +402,201 @@ fn main() -> Result<()> {
     let input = include_str!("../../input/day13.txt");
     let floors = parse(input)?;
 
-    let mirror_value_sum: usize = floors.iter().map(|floor| floor.mirror_value(false)).sum();
+    let mirror_value_sum = floors
+        .iter()
+        .map(|floor| Ok(floor.reflection(0)?.value()))
+        .sum::<Result<usize>>()?;
     println!("Note summarization value: {mirror_value_sum}");
 
-    let smudged_mirror_value_sum: usize = floors.iter().map(|floor| floor.mirror_value(true)).sum();
+    let smudged_reflections = floors
+        .iter()
+        .map(|floor| floor.reflection(1))
+        .collect::<Result<Vec<_>>>()?;
+    match std::env::args().nth(1).as_deref() {
+        Some("render") => {
+            let colour = std::io::stdout().is_terminal();
+            for (floor, reflection) in floors.iter().zip(&smudged_reflections) {
+                println!("{}\n", floor.render(&floor.reflection(0)?, colour));
+                println!("{}\n", floor.render(reflection, colour));
+            }
+        }
+        Some("bench") => {
+            let iterations = std::env::args().nth(2).map_or(Ok(100), |arg| arg.parse())?;
+            bench(&floors, iterations);
+        }
+        _ => {}
+    }
+
+    let smudged_mirror_value_sum: usize = smudged_reflections.iter().map(Reflection::value).sum();
     println!("Smudged note summarization value: {smudged_mirror_value_sum}");
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{parse, synthetic_floor, Axis, Reflection};
+    use advent_of_code_2023::coordinates::Coordinate;
+    use anyhow::Result;
+
+    const EXAMPLE: &str = "\
+        #.##..##.\n\
+        ..#.##.#.\n\
+        ##......#\n\
+        ##......#\n\
+        ..#.##.#.\n\
+        ..##..##.\n\
+        #.#.##.#.\n\
+        \n\
+        #...##..#\n\
+        #....#..#\n\
+        ..##..###\n\
+        #####.##.\n\
+        #####.##.\n\
+        ..##..###\n\
+        #....#..#";
+
+    fn smudge(x: usize, first: usize, second: usize, axis: Axis) -> [Coordinate; 2] {
+        match axis {
+            Axis::Horizontal => [Coordinate::new(x, first), Coordinate::new(x, second)],
+            Axis::Vertical => [Coordinate::new(first, x), Coordinate::new(second, x)],
+        }
+    }
+
+    #[test]
+    fn reflections() -> Result<()> {
+        let floors = parse(EXAMPLE)?;
+        assert_eq!(
+            floors[0].reflections(0),
+            [Reflection {
+                axis: Axis::Vertical,
+                index: 5,
+                smudges: vec![],
+            }]
+        );
+        assert_eq!(
+            floors[1].reflections(0),
+            [Reflection {
+                axis: Axis::Horizontal,
+                index: 4,
+                smudges: vec![],
+            }]
+        );
+        assert_eq!(
+            floors
+                .iter()
+                .map(|floor| Ok(floor.reflection(0)?.value()))
+                .sum::<Result<usize>>()?,
+            405
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn smudged_reflections() -> Result<()> {
+        let floors = parse(EXAMPLE)?;
+        assert_eq!(
+            floors[0].reflection(1)?,
+            Reflection {
+                axis: Axis::Horizontal,
+                index: 3,
+                smudges: vec![smudge(0, 0, 5, Axis::Horizontal)],
+            }
+        );
+        assert_eq!(
+            floors[1].reflection(1)?,
+            Reflection {
+                axis: Axis::Horizontal,
+                index: 1,
+                smudges: vec![smudge(4, 0, 1, Axis::Horizontal)],
+            }
+        );
+
+        // Two differences in one row pair, or spread over two column pairs
+        let floor = &parse("##..\n#..#")?[0];
+        assert_eq!(
+            floor.reflections(2),
+            [
+                Reflection {
+                    axis: Axis::Horizontal,
+                    index: 1,
+                    smudges: vec![
+                        smudge(1, 0, 1, Axis::Horizontal),
+                        smudge(3, 0, 1, Axis::Horizontal)
+                    ],
+                },
+                Reflection {
+                    axis: Axis::Vertical,
+                    index: 2,
+                    smudges: vec![
+                        smudge(0, 1, 2, Axis::Vertical),
+                        smudge(0, 0, 3, Axis::Vertical)
+                    ],
+                },
+            ]
+        );
+        assert_eq!(floor.reflections(2), floor.reflections_by_cell(2));
+        Ok(())
+    }
+
+    #[test]
+    fn no_reflection() -> Result<()> {
+        let floor = &parse("#.\n##")?[0];
+        let error = floor.reflection(0).unwrap_err().to_string();
+        assert!(error.starts_with("failed to find any mirrored planes"));
+        assert!(error.ends_with("#.\n##"));
+        Ok(())
+    }
+
+    #[test]
+    fn render() -> Result<()> {
+        let floors = parse(EXAMPLE)?;
+        let expected = [
+            "123456789",
+            "    ><   ",
+            "#.##..##.",
+            "..#.##.#.",
+            "##......#",
+            "##......#",
+            "..#.##.#.",
+            "..##..##.",
+            "#.#.##.#.",
+            "    ><   ",
+            "123456789",
+        ]
+        .join("\n");
+        assert_eq!(floors[0].render(&floors[0].reflection(0)?, false), expected);
+
+        let expected = "\
+            1 *.##..##. 1\n\
+            2 ..#.##.#. 2\n\
+            3v##......#v3\n\
+            4^##......#^4\n\
+            5 ..#.##.#. 5\n\
+            6 *.##..##. 6\n\
+            7 #.#.##.#. 7";
+        let smudged = floors[0].reflection(1)?;
+        assert_eq!(floors[0].render(&smudged, false), expected);
+        assert!(floors[0]
+            .render(&smudged, true)
+            .starts_with("1 \x1b[1;31m#\x1b[0m.##..##. 1"));
+        Ok(())
+    }
+
+    #[test]
+    fn bitset_matches_cell_by_cell() {
+        for (seed, (width, height)) in [(5, 7), (64, 64), (65, 30), (130, 131), (200, 200)]
+            .into_iter()
+            .enumerate()
+        {
+            for flipped in 0..3 {
+                let floor = synthetic_floor(width, height, flipped, seed as u64);
+                for smudges in 0..3 {
+                    assert_eq!(
+                        floor.reflections(smudges),
+                        floor.reflections_by_cell(smudges)
+                    );
+                }
+            }
+        }
+    }
+}
@@ -8,11 +8,9 @@ where
     Self: Sized,
 {
     fn disjoint(&self, other: &Self) -> bool;
-    fn contained_in(&self, other: &Self) -> bool;
     fn overlap(&self, other: &Self) -> bool;
     fn adjacent(&self, other: &Self) -> bool;
     fn union(&self, other: &Self) -> Option<Self>;
-    fn intersection(&self, other: &Self) -> Option<Self>;
 }
 
 impl<T> SetOperations for Range<T>
@@ -23,10 +21,6 @@ where
         self.end <= other.start || self.start >= other.end
     }
 
-    fn contained_in(&self, other: &Self) -> bool {
-        self.start >= other.start && self.end <= other.end
-    }
-
     fn overlap(&self, other: &Self) -> bool {
         (self.start >= other.start && self.start < other.end)
             || (self.end <= other.end && self.end > other.start)
@@ -43,14 +37,6 @@ where
             None
         }
     }
-
-    fn intersection(&self, other: &Self) -> Option<Self> {
-        if self.overlap(other) {
-            Some(self.start.max(other.start)..self.end.min(other.end))
-        } else {
-            None
-        }
-    }
 }
 
 struct RangeMapping {
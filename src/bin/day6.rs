@@ -1,18 +1,24 @@
+use advent_of_code_2023::solution::Overflow;
 use anyhow::{anyhow, Result};
 use derive_more::From;
+use thiserror::Error;
 use winnow::{
-    ascii::{dec_uint, line_ending, multispace1},
+    ascii::{dec_uint, digit1, line_ending, multispace1, space1},
     combinator::separated,
     prelude::*,
 };
 
+#[derive(Debug, Error)]
+#[error("kerned number with {0} digits does not fit in a u128")]
+struct KerningOverflow(usize);
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, From)]
 #[repr(transparent)]
-struct Milliseconds(u64);
+struct Milliseconds(u128);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, From)]
 #[repr(transparent)]
-struct Millimeters(u64);
+struct Millimeters(u128);
 
 #[derive(Debug)]
 struct Race {
@@ -21,18 +27,21 @@ struct Race {
 }
 
 impl Race {
-    fn distance_travelled(&self, button_held: Milliseconds) -> Millimeters {
+    // None if the distance does not fit in a u128
+    fn distance_travelled(&self, button_held: Milliseconds) -> Option<Millimeters> {
         let Some(remaining_time) = self.time.0.checked_sub(button_held.0) else {
-            return Millimeters(0);
+            return Some(Millimeters(0));
         };
-        Millimeters(button_held.0 * remaining_time)
+        button_held.0.checked_mul(remaining_time).map(Millimeters)
     }
 
+    // A distance too large for a u128 beats any record, since records are u128s
     fn win(&self, button_held: Milliseconds) -> bool {
-        self.distance_travelled(button_held) > self.distance
+        self.distance_travelled(button_held)
+            .is_none_or(|distance| distance > self.distance)
     }
 
-    fn winning_moves_count(&self) -> u64 {
+    fn winning_moves_count(&self) -> u128 {
         let Some(first) = (1..self.time.0).find(|&button_held| self.win(Milliseconds(button_held)))
         else {
             return 0;
@@ -51,33 +60,42 @@ impl Race {
 struct Races(Vec<Race>);
 
 impl Races {
-    fn combine_races(&self) -> Result<Race> {
-        let distance = Millimeters(
-            self.0
-                .iter()
-                .map(|race| race.distance.0.to_string())
-                .collect::<String>()
-                .parse()?,
-        );
-        let time = Milliseconds(
-            self.0
-                .iter()
-                .map(|race| race.time.0.to_string())
-                .collect::<String>()
-                .parse()?,
-        );
-
-        Ok(Race { time, distance })
+    fn winning_moves_product(&self) -> Result<u128, Overflow> {
+        self.0
+            .iter()
+            .try_fold(1u128, |product, race| {
+                product.checked_mul(race.winning_moves_count())
+            })
+            .ok_or(Overflow("product of winning moves"))
     }
 }
 
 fn parse_nums<T>(i: &mut &str) -> PResult<Vec<T>>
 where
-    T: From<u64>,
+    T: From<u128>,
 {
     separated(1.., dec_uint.map(T::from), multispace1).parse_next(i)
 }
 
+// Reads a line of numbers as a single number, ignoring the spaces between them
+fn parse_kerned_num<T>(i: &mut &str) -> PResult<T>
+where
+    T: From<u128>,
+{
+    separated(1.., digit1, space1)
+        .try_map(|digits: Vec<&str>| {
+            digits
+                .iter()
+                .flat_map(|digits| digits.bytes())
+                .try_fold(0u128, |num, digit| {
+                    num.checked_mul(10)?.checked_add((digit - b'0').into())
+                })
+                .map(T::from)
+                .ok_or_else(|| KerningOverflow(digits.iter().map(|digits| digits.len()).sum()))
+        })
+        .parse_next(i)
+}
+
 fn parser(i: &mut &str) -> PResult<Races> {
     let (_, _, times, _, _, _, distances) = (
         "Time:",
@@ -98,27 +116,105 @@ fn parser(i: &mut &str) -> PResult<Races> {
     ))
 }
 
+fn kerned_parser(i: &mut &str) -> PResult<Race> {
+    let (_, _, time, _, _, _, distance) = (
+        "Time:",
+        space1,
+        parse_kerned_num,
+        line_ending,
+        "Distance:",
+        space1,
+        parse_kerned_num,
+    )
+        .parse_next(i)?;
+    Ok(Race { time, distance })
+}
+
 fn parse(i: &str) -> Result<Races> {
     parser.parse(i).map_err(|e| anyhow!(e.to_string()))
 }
 
+fn parse_kerned(i: &str) -> Result<Race> {
+    kerned_parser.parse(i).map_err(|e| anyhow!(e.to_string()))
+}
+
 fn main() -> Result<()> {
     let input = include_str!("../../input/day6.txt");
     let races = parse(input)?;
 
     println!(
         "Ways of winning multiplied: {}",
-        races
-            .0
-            .iter()
-            .map(Race::winning_moves_count)
-            .product::<u64>()
+        races.winning_moves_product()?
     );
 
     println!(
         "Ways of winning large race: {}",
-        races.combine_races()?.winning_moves_count()
+        parse_kerned(input)?.winning_moves_count()
     );
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        kerned_parser, parse, parse_kerned, KerningOverflow, Millimeters, Milliseconds, Race, Races,
+    };
+    use anyhow::Result;
+    use winnow::Parser;
+
+    const INPUT: &str = "\
+        Time:      7  15   30\n\
+        Distance:  9  40  200";
+
+    #[test]
+    fn kerned() -> Result<()> {
+        let race = parse_kerned(INPUT)?;
+        assert_eq!(race.time.0, 71530);
+        assert_eq!(race.distance.0, 940200);
+        assert_eq!(race.winning_moves_count(), 71503);
+        assert_eq!(parse(INPUT)?.winning_moves_product()?, 288);
+        Ok(())
+    }
+
+    #[test]
+    fn kerning_overflow() {
+        // u128::MAX split over the line fits, one more does not
+        let max = "Time: 3402823669209 384634633746 07431768211455\nDistance: 1";
+        assert_eq!(
+            parse_kerned(max).map(|race| race.time.0).ok(),
+            Some(u128::MAX)
+        );
+
+        let too_big = "Time: 3402823669209 384634633746 07431768211456\nDistance: 1";
+        let error = kerned_parser.parse(too_big).unwrap_err();
+        let cause = error.inner().cause().and_then(|cause| cause.downcast_ref());
+        assert!(matches!(cause, Some(KerningOverflow(39))));
+    }
+
+    #[test]
+    fn product_overflow() {
+        let race = || Race {
+            time: Milliseconds(1 << 100),
+            distance: 0.into(),
+        };
+        assert!(Races(vec![race()]).winning_moves_product().is_ok());
+        assert!(Races(vec![race(), race()]).winning_moves_product().is_err());
+    }
+
+    #[test]
+    fn distance_overflow() {
+        // Holding for 2^64 of 2^65 ms travels 2^128 mm, one more than the record
+        let race = Race {
+            time: Milliseconds(1 << 65),
+            distance: Millimeters(u128::MAX),
+        };
+        assert_eq!(race.distance_travelled(Milliseconds(1 << 64)), None);
+        assert!(race.win(Milliseconds(1 << 64)));
+        assert_eq!(
+            race.distance_travelled(Milliseconds(1)),
+            Some(Millimeters((1 << 65) - 1))
+        );
+        assert!(!race.win(Milliseconds(1)));
+    }
+}
@@ -1,5 +1,6 @@
-use parse::parse;
-use std::{cmp::Ordering, collections::HashMap, str::FromStr};
+use advent_of_code_2023::random::XorShift;
+use parse::{parse, parse_cards};
+use std::{cmp::Ordering, str::FromStr, time::Instant};
 use strum::{EnumIter, IntoEnumIterator};
 use thiserror::Error;
 
@@ -9,9 +10,17 @@ pub enum Error {
     CardParseError(String),
     #[error("failed to parse card line {0}")]
     LineParsingError(String),
+    #[error("card ordering must list every card exactly once, got {0:?}")]
+    CardOrderError(Vec<Card>),
+    #[error("invalid benchmark size {0}")]
+    BenchSizeError(String),
 }
 
 pub type DayResult<T> = Result<T, Error>;
+
+// Longest hands whose type and card ranks still fit in a u64 sort key, at 4 bits a card
+const MAX_PACKED_CARDS: usize = 15;
+
 #[derive(Debug, PartialEq, Eq, EnumIter, Clone, Copy, Hash)]
 pub enum Card {
     Two,
@@ -29,19 +38,48 @@ pub enum Card {
     A,
 }
 
-impl Card {
-    fn cmp(self, other: Self, no_joker: bool) -> Ordering {
-        let compare = (self as u32).cmp(&(other as u32));
-        if no_joker {
-            compare
-        } else {
-            match (self, other) {
-                (Self::J, Self::J) => Ordering::Equal,
-                (Self::J, _) => Ordering::Less,
-                (_, Self::J) => Ordering::Greater,
-                _ => compare,
-            }
+#[derive(Debug, Clone)]
+pub struct Rules {
+    // Rank of each card, indexed by the card's standard position
+    ranks: [u8; 13],
+    // Cards that count as whichever card makes the strongest hand
+    wildcards: Vec<Card>,
+}
+
+impl Rules {
+    // Takes the cards from weakest to strongest
+    pub fn new(order: Vec<Card>, wildcards: Vec<Card>) -> DayResult<Self> {
+        let mut ranks = [None; 13];
+        for (rank, &card) in order.iter().enumerate() {
+            ranks[card as usize] = Some(rank as u8);
         }
+        if order.len() != ranks.len() || ranks.contains(&None) {
+            return Err(Error::CardOrderError(order));
+        }
+        Ok(Self {
+            ranks: ranks.map(Option::unwrap),
+            wildcards,
+        })
+    }
+
+    pub fn standard() -> Self {
+        Self::new(Card::iter().collect(), vec![]).unwrap()
+    }
+
+    // Jokers are wild, but are the weakest card when breaking ties
+    pub fn joker_wild() -> Self {
+        let order = std::iter::once(Card::J)
+            .chain(Card::iter().filter(|&card| card != Card::J))
+            .collect();
+        Self::new(order, vec![Card::J]).unwrap()
+    }
+
+    fn rank(&self, card: Card) -> u8 {
+        self.ranks[card as usize]
+    }
+
+    fn is_wild(&self, card: Card) -> bool {
+        self.wildcards.contains(&card)
     }
 }
 
@@ -86,21 +124,26 @@ pub struct Hand {
 }
 
 impl Hand {
-    fn hand_type(&self, no_joker: bool) -> HandType {
-        let mut map: HashMap<_, _> = Card::iter().map(|card| (card, 0)).collect();
-        for card in &self.cards {
-            *map.get_mut(card).unwrap() += 1;
+    fn hand_type(&self, rules: &Rules) -> HandType {
+        let mut counts = [0; 13];
+        let mut wild_count = 0;
+        for &card in &self.cards {
+            if rules.is_wild(card) {
+                wild_count += 1;
+            } else {
+                counts[card as usize] += 1;
+            }
+        }
+        let (mut max_count, mut second_max_count) = (0, 0);
+        for count in counts {
+            if count > max_count {
+                (max_count, second_max_count) = (count, max_count);
+            } else if count > second_max_count {
+                second_max_count = count;
+            }
         }
-        let j_count = if no_joker {
-            0
-        } else {
-            map.remove(&Card::J).unwrap_or(0)
-        };
-        let max_card = *map.iter().max_by_key(|(_, &i)| i).unwrap().0;
-        let max_count = map.remove(&max_card).unwrap_or(0);
-        let second_max_count = map.values().max().copied().unwrap_or(0);
 
-        match (max_count + j_count, second_max_count) {
+        match (max_count + wild_count, second_max_count) {
             (5, _) => HandType::FiveOfAKind,
             (4, _) => HandType::FourOfAKind,
             (3, 2) => HandType::FullHouse,
@@ -111,15 +154,24 @@ impl Hand {
         }
     }
 
-    fn cmp(&self, other: &Self, no_joker: bool) -> Ordering {
-        self.hand_type(no_joker)
-            .cmp(&other.hand_type(no_joker))
+    // Orders the same as cmp for hands of equal length, up to MAX_PACKED_CARDS
+    fn sort_key(&self, rules: &Rules) -> u64 {
+        self.cards
+            .iter()
+            .fold(self.hand_type(rules) as u64, |key, &card| {
+                key << 4 | u64::from(rules.rank(card))
+            })
+    }
+
+    fn cmp(&self, other: &Self, rules: &Rules) -> Ordering {
+        self.hand_type(rules)
+            .cmp(&other.hand_type(rules))
             .then_with(|| {
                 self.cards
                     .iter()
                     .zip(other.cards.iter())
-                    .fold(Ordering::Equal, |order, (x, &y)| {
-                        order.then_with(|| x.cmp(y, no_joker))
+                    .fold(Ordering::Equal, |order, (&x, &y)| {
+                        order.then_with(|| rules.rank(x).cmp(&rules.rank(y)))
                     })
             })
     }
@@ -131,8 +183,21 @@ pub struct Game {
 }
 
 impl Game {
-    fn winnings(&mut self, no_joker: bool) -> u64 {
-        self.hands.sort_by(|x, y| x.cmp(y, no_joker));
+    fn winnings(&mut self, rules: &Rules) -> u64 {
+        let len = self.hands.first().map_or(0, |hand| hand.cards.len());
+        if len > MAX_PACKED_CARDS || self.hands.iter().any(|hand| hand.cards.len() != len) {
+            return self.winnings_by_comparison(rules);
+        }
+        self.hands.sort_by_cached_key(|hand| hand.sort_key(rules));
+        self.total_winnings()
+    }
+
+    fn winnings_by_comparison(&mut self, rules: &Rules) -> u64 {
+        self.hands.sort_by(|x, y| x.cmp(y, rules));
+        self.total_winnings()
+    }
+
+    fn total_winnings(&self) -> u64 {
         self.hands
             .iter()
             .enumerate()
@@ -141,6 +206,38 @@ impl Game {
     }
 }
 
+fn synthetic_game(hands: usize, seed: u64) -> Game {
+    let mut random = XorShift::new(seed);
+    let cards: Vec<_> = Card::iter().collect();
+    let hands = (0..hands)
+        .map(|_| Hand {
+            cards: (0..5).map(|_| cards[random.below(cards.len())]).collect(),
+            bid: random.next_u64() % 1000 + 1,
+        })
+        .collect();
+    Game { hands }
+}
+
+fn bench(hands: usize) {
+    for (name, rules) in [
+        ("standard", Rules::standard()),
+        ("joker wild", Rules::joker_wild()),
+    ] {
+        let mut game = synthetic_game(hands, 7);
+        let start = Instant::now();
+        let by_comparison = std::hint::black_box(game.winnings_by_comparison(&rules));
+        let comparison = start.elapsed();
+
+        let mut game = synthetic_game(hands, 7);
+        let start = Instant::now();
+        let by_key = std::hint::black_box(game.winnings(&rules));
+        let cached = start.elapsed();
+
+        assert_eq!(by_comparison, by_key);
+        println!("{name} rules, {hands} hands: comparison {comparison:?}, cached key {cached:?}");
+    }
+}
+
 mod parse {
     use crate::{Card, DayResult, Error, Game, Hand};
     use std::str::FromStr;
@@ -169,18 +266,200 @@ mod parse {
         game.parse(input)
             .map_err(|e| Error::LineParsingError(e.to_string()))
     }
+
+    pub fn parse_cards(input: &str) -> DayResult<Vec<Card>> {
+        repeat(0.., card)
+            .parse(input)
+            .map_err(|_| Error::CardParseError(input.to_owned()))
+    }
 }
 
 fn main() -> DayResult<()> {
     let input = include_str!("../../input/day7.txt");
     let mut game = parse(input)?;
-    let winnings = game.winnings(true);
+
+    // Custom rules as `day7 <cards weakest to strongest> [wildcards]`,
+    // e.g. `day7 J23456789TQKA J` for the joker rules, or `day7 bench [hands]`
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("bench") {
+        let hands = args.get(1).map_or(Ok(100_000), |arg| {
+            arg.parse().map_err(|_| Error::BenchSizeError(arg.clone()))
+        })?;
+        bench(hands);
+        return Ok(());
+    }
+    if let Some(order) = args.first() {
+        let wildcards = args.get(1).map_or(Ok(vec![]), |cards| parse_cards(cards))?;
+        let rules = Rules::new(parse_cards(order)?, wildcards)?;
+        println!("Winnings with custom rules: {}", game.winnings(&rules));
+        return Ok(());
+    }
+
+    let winnings = game.winnings(&Rules::standard());
     println!("Winnings: {winnings}");
     assert_eq!(winnings, 253_910_319);
 
-    let winnings_with_joker = game.winnings(false);
+    let winnings_with_joker = game.winnings(&Rules::joker_wild());
     println!("Winnings with joker: {winnings_with_joker}");
     assert_eq!(winnings_with_joker, 254_083_736);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        parse, parse_cards, synthetic_game, Card, Error, Game, Hand, HandType, Rules,
+        MAX_PACKED_CARDS,
+    };
+    use advent_of_code_2023::random::XorShift;
+    use anyhow::Result;
+    use strum::IntoEnumIterator;
+
+    const EXAMPLE: &str = "\
+        32T3K 765\n\
+        T55J5 684\n\
+        KK677 28\n\
+        KTJJT 220\n\
+        QQQJA 483";
+
+    fn hand(cards: &str) -> Result<Hand> {
+        Ok(Hand {
+            cards: parse_cards(cards)?,
+            bid: 1,
+        })
+    }
+
+    fn all_rules() -> Result<Vec<Rules>> {
+        Ok(vec![
+            Rules::standard(),
+            Rules::joker_wild(),
+            Rules::new(parse_cards("AKQJT98765432")?, vec![Card::Two, Card::Nine])?,
+        ])
+    }
+
+    #[test]
+    fn sort_key_matches_comparison() -> Result<()> {
+        for rules in all_rules()? {
+            let game = synthetic_game(2000, 3);
+            for pair in game.hands.windows(2) {
+                assert_eq!(
+                    pair[0].sort_key(&rules).cmp(&pair[1].sort_key(&rules)),
+                    pair[0].cmp(&pair[1], &rules)
+                );
+            }
+            let (mut by_key, mut by_comparison) =
+                (synthetic_game(2000, 3), synthetic_game(2000, 3));
+            assert_eq!(
+                by_key.winnings(&rules),
+                by_comparison.winnings_by_comparison(&rules)
+            );
+
+            let mut example = parse(EXAMPLE)?;
+            assert_eq!(
+                example.winnings(&rules),
+                parse(EXAMPLE)?.winnings_by_comparison(&rules)
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn unpackable_hands() -> Result<()> {
+        // Packing would let the card ranks of the longer hand outweigh the shorter hand's
+        // better type
+        let mut game = Game {
+            hands: vec![
+                Hand {
+                    cards: parse_cards("22")?,
+                    bid: 1,
+                },
+                Hand {
+                    cards: parse_cards("AKQ")?,
+                    bid: 10,
+                },
+            ],
+        };
+        assert_eq!(game.winnings(&Rules::standard()), 12);
+        assert_eq!(game.winnings_by_comparison(&Rules::standard()), 12);
+
+        let mut random = XorShift::new(5);
+        let cards: Vec<_> = Card::iter().collect();
+        let mut game = Game {
+            hands: (0..200)
+                .map(|_| Hand {
+                    cards: (0..MAX_PACKED_CARDS + 1)
+                        .map(|_| cards[random.below(cards.len())])
+                        .collect(),
+                    bid: 1 + random.next_u64() % 1000,
+                })
+                .collect(),
+        };
+        let mut by_comparison = Game {
+            hands: game
+                .hands
+                .iter()
+                .map(|hand| Hand {
+                    cards: hand.cards.clone(),
+                    bid: hand.bid,
+                })
+                .collect(),
+        };
+        for rules in all_rules()? {
+            assert_eq!(
+                game.winnings(&rules),
+                by_comparison.winnings_by_comparison(&rules)
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rules() -> Result<()> {
+        let mut game = parse(EXAMPLE)?;
+        assert_eq!(game.winnings(&Rules::standard()), 6440);
+        assert_eq!(game.winnings(&Rules::joker_wild()), 5905);
+
+        // Spelling out the joker rules gives the same result
+        let custom = Rules::new(parse_cards("J23456789TQKA")?, vec![Card::J])?;
+        assert_eq!(game.winnings(&custom), 5905);
+
+        let reversed = Rules::new(parse_cards("AKQJT98765432")?, vec![])?;
+        assert!(reversed.rank(Card::A) < reversed.rank(Card::Two));
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_orderings() -> Result<()> {
+        let missing = parse_cards("23456789TJQK")?;
+        assert!(matches!(
+            Rules::new(missing, vec![]),
+            Err(Error::CardOrderError(order)) if order.len() == 12
+        ));
+        let duplicated = parse_cards("23456789TJQKK")?;
+        assert!(matches!(
+            Rules::new(duplicated, vec![]),
+            Err(Error::CardOrderError(_))
+        ));
+        let extra = parse_cards("23456789TJQKAA")?;
+        assert!(matches!(
+            Rules::new(extra, vec![]),
+            Err(Error::CardOrderError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn wildcards() -> Result<()> {
+        let rules = Rules::new(parse_cards("23456789TJQKA")?, vec![Card::J, Card::Q])?;
+        assert_eq!(hand("JQ234")?.hand_type(&rules), HandType::ThreeOfAKind);
+        assert_eq!(hand("JQ223")?.hand_type(&rules), HandType::FourOfAKind);
+        assert_eq!(hand("J2233")?.hand_type(&rules), HandType::FullHouse);
+        assert_eq!(hand("JQJQJ")?.hand_type(&rules), HandType::FiveOfAKind);
+        assert_eq!(
+            hand("JQ223")?.hand_type(&Rules::standard()),
+            HandType::OnePair
+        );
+        Ok(())
+    }
+}
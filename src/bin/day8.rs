@@ -1,53 +1,448 @@
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use num::Integer;
 use parse::parse;
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
 
 #[derive(Debug)]
 struct Graph<T>(HashMap<T, Node<T>>);
 
 impl<'a> Graph<&'a str> {
-    fn count_steps(&self, directions: &[Direction]) -> Option<usize> {
-        let mut current_node = self.0.get("AAA")?;
-        for (count, dir) in directions.iter().cycle().enumerate() {
-            let next_node = *current_node.turn(*dir);
-            if next_node == "ZZZ" {
-                return Some(count + 1);
+    fn starts<'b>(
+        &'b self,
+        is_start: &'b impl Fn(&str) -> bool,
+    ) -> impl Iterator<Item = &'a str> + 'b {
+        self.0.keys().filter(|name| is_start(name)).copied()
+    }
+
+    fn neighbours(&self, name: &str) -> impl Iterator<Item = &'a str> + '_ {
+        self.0
+            .get(name)
+            .into_iter()
+            .flat_map(|node| [node.left, node.right])
+    }
+
+    // Nodes reachable by taking either turn at every step
+    fn reachable(&self, from: &'a str) -> HashSet<&'a str> {
+        let mut reached = HashSet::from([from]);
+        let mut stack = vec![from];
+        while let Some(name) = stack.pop() {
+            for next in self.neighbours(name) {
+                if reached.insert(next) {
+                    stack.push(next);
+                }
             }
-            current_node = self.0.get(next_node)?;
         }
-        None
+        reached
     }
 
-    fn count_steps_multiple(&self, directions: &[Direction]) -> Option<usize> {
-        self.0
+    fn reachable_goals(
+        &self,
+        is_start: &impl Fn(&str) -> bool,
+        is_goal: &impl Fn(&str) -> bool,
+    ) -> Vec<(&'a str, Vec<&'a str>)> {
+        let mut goals: Vec<_> = self
+            .starts(is_start)
+            .map(|start| {
+                let mut goals: Vec<_> = self
+                    .reachable(start)
+                    .into_iter()
+                    .filter(|name| is_goal(name))
+                    .collect();
+                goals.sort_unstable();
+                (start, goals)
+            })
+            .collect();
+        goals.sort_unstable();
+        goals
+    }
+
+    fn unreachable(&self, is_start: &impl Fn(&str) -> bool) -> Vec<&'a str> {
+        let reached: HashSet<_> = self
+            .starts(is_start)
+            .flat_map(|start| self.reachable(start))
+            .collect();
+        let mut unreachable: Vec<_> = self
+            .0
             .keys()
-            .filter(|name| name.as_bytes()[2] == b'A')
+            .filter(|name| !reached.contains(*name))
             .copied()
-            .map(|name| {
-                let mut current_node = self.0.get(name).unwrap();
-                let mut next_node_name = name;
-                let mut visited = vec![];
-                for dir in directions.iter().cycle() {
-                    next_node_name = current_node.turn(*dir);
-                    if visited.contains(&next_node_name) {
+            .collect();
+        unreachable.sort_unstable();
+        unreachable
+    }
+
+    // Edges a ghost from start keeps following once it has settled into its loop
+    fn ghost_cycle(&self, directions: &[Direction], start: &'a str) -> Vec<(&'a str, Direction)> {
+        let mut first_seen = HashMap::new();
+        let mut path = vec![];
+        let mut name = start;
+        for (i, &dir) in directions.iter().enumerate().cycle() {
+            if let Some(&loop_start) = first_seen.get(&(name, i)) {
+                return path.split_off(loop_start);
+            }
+            first_seen.insert((name, i), path.len());
+            path.push((name, dir));
+            let Some(node) = self.0.get(name) else {
+                return vec![];
+            };
+            name = *node.turn(dir);
+        }
+        vec![]
+    }
+
+    fn to_dot(
+        &self,
+        is_start: &impl Fn(&str) -> bool,
+        is_goal: &impl Fn(&str) -> bool,
+        highlighted: &HashSet<(&str, Direction)>,
+    ) -> String {
+        let mut names: Vec<_> = self.0.keys().copied().collect();
+        names.sort_unstable();
+
+        let mut dot = String::from("digraph network {\n    node [shape=circle];\n");
+        for &name in &names {
+            let style = match (is_start(name), is_goal(name)) {
+                (true, true) => "shape=doubleoctagon, style=filled, fillcolor=gold",
+                (true, false) => "shape=box, style=filled, fillcolor=palegreen",
+                (false, true) => "shape=doublecircle, style=filled, fillcolor=lightcoral",
+                (false, false) => continue,
+            };
+            dot += &format!("    \"{name}\" [{style}];\n");
+        }
+        for &name in &names {
+            let node = &self.0[name];
+            // Both turns leading to the same node are drawn as a single edge
+            let edges = if node.left == node.right {
+                vec![(node.left, "LR", vec![Direction::Left, Direction::Right])]
+            } else {
+                vec![
+                    (node.left, "L", vec![Direction::Left]),
+                    (node.right, "R", vec![Direction::Right]),
+                ]
+            };
+            for (next, label, directions) in edges {
+                let highlight = if directions
+                    .into_iter()
+                    .any(|dir| highlighted.contains(&(name, dir)))
+                {
+                    ", color=red, penwidth=2"
+                } else {
+                    ""
+                };
+                dot += &format!("    \"{name}\" -> \"{next}\" [label=\"{label}\"{highlight}];\n");
+            }
+        }
+        dot += "}\n";
+        dot
+    }
+
+    // Tarjan's algorithm
+    fn strongly_connected_components(&self) -> Vec<Vec<&'a str>> {
+        #[derive(Default)]
+        struct State<'a> {
+            index: HashMap<&'a str, usize>,
+            low: HashMap<&'a str, usize>,
+            stack: Vec<&'a str>,
+            on_stack: HashSet<&'a str>,
+            components: Vec<Vec<&'a str>>,
+        }
+
+        fn visit<'a>(graph: &Graph<&'a str>, name: &'a str, state: &mut State<'a>) {
+            let index = state.index.len();
+            state.index.insert(name, index);
+            state.low.insert(name, index);
+            state.stack.push(name);
+            state.on_stack.insert(name);
+
+            for next in graph.neighbours(name) {
+                let low = if !state.index.contains_key(next) {
+                    visit(graph, next, state);
+                    state.low[next]
+                } else if state.on_stack.contains(next) {
+                    state.index[next]
+                } else {
+                    continue;
+                };
+                let current = state.low[name].min(low);
+                state.low.insert(name, current);
+            }
+
+            if state.low[name] == index {
+                let mut component = vec![];
+                while let Some(member) = state.stack.pop() {
+                    state.on_stack.remove(member);
+                    component.push(member);
+                    if member == name {
                         break;
                     }
-                    visited.push(next_node_name);
-                    current_node = self.0.get(next_node_name).unwrap();
                 }
-                let loop_start = visited
-                    .iter()
-                    .position(|&name| name == next_node_name)
-                    .unwrap();
-                let loop_length = visited.len() - loop_start;
-                loop_length.lcm(&directions.len())
+                component.sort_unstable();
+                state.components.push(component);
+            }
+        }
+
+        let mut state = State::default();
+        let mut names: Vec<_> = self.0.keys().copied().collect();
+        names.sort_unstable();
+        for name in names {
+            if !state.index.contains_key(name) {
+                visit(self, name, &mut state);
+            }
+        }
+        state.components
+    }
+}
+
+// Graph with names interned to dense IDs so walking needs no string hashing
+#[derive(Debug)]
+struct DenseGraph<'a> {
+    names: Vec<&'a str>,
+    ids: HashMap<&'a str, u32>,
+    adjacency: Vec<[u32; 2]>,
+    directions: Vec<Direction>,
+    // cycle_jumps[k][node] is where node ends up after 2^k passes through the directions
+    cycle_jumps: Vec<Vec<u32>>,
+}
+
+impl<'a> DenseGraph<'a> {
+    // None if a node turns towards a name that is not in the graph
+    fn new(graph: &Graph<&'a str>, directions: &[Direction]) -> Option<Self> {
+        let mut names: Vec<_> = graph.0.keys().copied().collect();
+        names.sort_unstable();
+        let ids: HashMap<_, _> = names
+            .iter()
+            .enumerate()
+            .map(|(id, &name)| Some((name, u32::try_from(id).ok()?)))
+            .collect::<Option<_>>()?;
+        let adjacency = names
+            .iter()
+            .map(|name| {
+                let node = &graph.0[name];
+                Some([
+                    *ids.get(node.turn(Direction::Left))?,
+                    *ids.get(node.turn(Direction::Right))?,
+                ])
+            })
+            .collect::<Option<_>>()?;
+
+        let mut dense = Self {
+            names,
+            ids,
+            adjacency,
+            directions: directions.to_vec(),
+            cycle_jumps: vec![],
+        };
+        let single_pass = (0..dense.adjacency.len() as u32)
+            .map(|node| dense.walk(node, directions.len()))
+            .collect();
+        // Enough levels to cover any number of passes that fits in a u64 step count
+        let max_passes = u64::MAX / directions.len().max(1) as u64;
+        let levels = (u64::BITS - max_passes.leading_zeros()) as usize;
+        dense.cycle_jumps.push(single_pass);
+        for _ in 1..levels {
+            let previous = dense.cycle_jumps.last().unwrap();
+            let next = previous
+                .iter()
+                .map(|&node| previous[node as usize])
+                .collect();
+            dense.cycle_jumps.push(next);
+        }
+        Some(dense)
+    }
+
+    fn id(&self, name: &str) -> Option<u32> {
+        self.ids.get(name).copied()
+    }
+
+    fn name(&self, id: u32) -> &'a str {
+        self.names[id as usize]
+    }
+
+    fn turn(&self, node: u32, direction: Direction) -> u32 {
+        self.adjacency[node as usize][direction as usize]
+    }
+
+    // Walk from the start of the directions, for fewer steps than one pass
+    fn walk(&self, node: u32, steps: usize) -> u32 {
+        self.directions[..steps]
+            .iter()
+            .fold(node, |node, &dir| self.turn(node, dir))
+    }
+
+    // Jumps whole passes through the directions by binary lifting, then walks the rest
+    fn position_after(&self, start: u32, steps: u64) -> u32 {
+        let len = self.directions.len() as u64;
+        let (mut passes, remainder) = (steps / len, steps % len);
+        let mut node = start;
+        for jumps in &self.cycle_jumps {
+            if passes == 0 {
+                break;
+            }
+            if passes & 1 == 1 {
+                node = jumps[node as usize];
+            }
+            passes >>= 1;
+        }
+        self.walk(node, remainder as usize)
+    }
+
+    fn goals(&self, is_goal: &impl Fn(&str) -> bool) -> Vec<bool> {
+        self.names.iter().map(|name| is_goal(name)).collect()
+    }
+
+    // None if no goal is ever reached, found by the walk returning to a node at the same
+    // point in the directions
+    fn steps_to_goal(&self, start: u32, goals: &[bool]) -> Option<usize> {
+        let len = self.directions.len();
+        let mut seen = vec![false; self.adjacency.len() * len];
+        let mut node = start;
+        for (count, (i, &dir)) in self.directions.iter().enumerate().cycle().enumerate() {
+            node = self.turn(node, dir);
+            if goals[node as usize] {
+                return Some(count + 1);
+            }
+            let state = &mut seen[node as usize * len + i];
+            if *state {
+                return None;
+            }
+            *state = true;
+        }
+        None
+    }
+
+    fn count_steps(&self, start: &str, is_goal: &impl Fn(&str) -> bool) -> Option<usize> {
+        self.steps_to_goal(self.id(start)?, &self.goals(is_goal))
+    }
+
+    // None if some ghost settles into a loop without a goal node on it
+    fn count_steps_multiple(
+        &self,
+        is_start: &impl Fn(&str) -> bool,
+        is_goal: &impl Fn(&str) -> bool,
+    ) -> Option<usize> {
+        let goals = self.goals(is_goal);
+        let loops: Option<Vec<_>> = (0..self.adjacency.len() as u32)
+            .filter(|&node| is_start(self.name(node)))
+            .map(|start| {
+                let mut first_visit = vec![None; self.adjacency.len()];
+                let mut path = vec![];
+                let mut node = start;
+                for (step, &dir) in self.directions.iter().cycle().enumerate() {
+                    node = self.turn(node, dir);
+                    if let Some(first) = first_visit[node as usize] {
+                        if !path[first..].iter().any(|&node| goals[node as usize]) {
+                            return None;
+                        }
+                        let loop_length: usize = step - first;
+                        return Some(loop_length.lcm(&self.directions.len()));
+                    }
+                    first_visit[node as usize] = Some(step);
+                    path.push(node);
+                }
+                None
             })
-            .reduce(|x, y| x.lcm(&y))
+            .collect();
+        loops?.into_iter().reduce(|x, y| x.lcm(&y))
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+// Glob over node names, where '?' matches any one character and '*' any run of them
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pattern(Vec<char>);
+
+impl Pattern {
+    fn new(pattern: &str) -> Self {
+        Self(pattern.chars().collect())
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        let name: Vec<_> = name.chars().collect();
+        let (mut p, mut n) = (0, 0);
+        // Position of the last '*' and the name position it was tried against
+        let mut backtrack = None;
+        while n < name.len() {
+            match self.0.get(p) {
+                Some('*') => {
+                    backtrack = Some((p, n));
+                    p += 1;
+                }
+                Some(&c) if c == '?' || c == name[n] => {
+                    p += 1;
+                    n += 1;
+                }
+                _ => match backtrack {
+                    Some((star, tried)) => {
+                        backtrack = Some((star, tried + 1));
+                        p = star + 1;
+                        n = tried + 1;
+                    }
+                    None => return false,
+                },
+            }
+        }
+        self.0[p..].iter().all(|&c| c == '*')
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.iter().collect::<String>())
+    }
+}
+
+// Patterns for the single walk and for the ghosts, the number of steps to simulate, and
+// where to export the network as DOT
+#[derive(Debug)]
+struct Options {
+    start: Pattern,
+    goal: Pattern,
+    ghost_start: Pattern,
+    ghost_goal: Pattern,
+    steps: Option<u64>,
+    dot: Option<String>,
+    cycles: bool,
+}
+
+impl Options {
+    fn from_args(args: &[&str]) -> Result<Self> {
+        let mut options = Self {
+            start: Pattern::new("AAA"),
+            goal: Pattern::new("ZZZ"),
+            ghost_start: Pattern::new("*A"),
+            ghost_goal: Pattern::new("*Z"),
+            steps: None,
+            dot: None,
+            cycles: false,
+        };
+        let mut args = args.iter();
+        while let Some(&arg) = args.next() {
+            if arg == "--cycles" {
+                options.cycles = true;
+                continue;
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow!("missing value for {arg}"))?;
+            match arg {
+                "--start" => options.start = Pattern::new(value),
+                "--goal" => options.goal = Pattern::new(value),
+                "--ghost-start" => options.ghost_start = Pattern::new(value),
+                "--ghost-goal" => options.ghost_goal = Pattern::new(value),
+                "--dot" => options.dot = Some(value.to_string()),
+                "--steps" => {
+                    options.steps = Some(value.parse().context("invalid number of steps")?)
+                }
+                _ => return Err(anyhow!("unknown argument {arg}")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Direction {
     Left,
     Right,
@@ -111,13 +506,301 @@ fn main() -> Result<()> {
     let input = include_str!("../../input/day8.txt");
     let (directions, graph) = parse(input)?;
 
+    // Options setting the start and goal patterns of part 1 and of the ghosts, a number of
+    // steps after which to show where each ghost is, and a file to export the network to as
+    // DOT with the ghost patterns styled and optionally their loops highlighted, e.g.
+    // `day8 --ghost-start '??A' --ghost-goal '??Z' --steps 100 --dot network.dot --cycles`
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    let args: Vec<_> = args.iter().map(String::as_str).collect();
+    let options = Options::from_args(&args)?;
+    let is_start = |name: &str| options.ghost_start.matches(name);
+    let is_goal = |name: &str| options.ghost_goal.matches(name);
+
+    if let Some(file) = &options.dot {
+        let highlighted = if options.cycles {
+            graph
+                .starts(&is_start)
+                .flat_map(|start| graph.ghost_cycle(&directions, start))
+                .collect()
+        } else {
+            HashSet::new()
+        };
+        std::fs::write(file, graph.to_dot(&is_start, &is_goal, &highlighted))?;
+    }
+
+    for (start, goals) in graph.reachable_goals(&is_start, &is_goal) {
+        println!("{start} can reach {}", goals.join(", "));
+    }
+    let unreachable = graph.unreachable(&is_start);
+    println!("Nodes unreachable from any start: {}", unreachable.len());
+    let components = graph.strongly_connected_components();
     println!(
-        "Number of steps from \"AAA\" to \"ZZZ\": {}",
-        graph.count_steps(&directions).unwrap()
-    );
-    println!(
-        "Fewest steps for each path to reach their destination at once: {}",
-        graph.count_steps_multiple(&directions).unwrap()
+        "Strongly connected components: {}, largest has {} nodes",
+        components.len(),
+        components.iter().map(Vec::len).max().unwrap_or(0)
     );
+
+    let dense = DenseGraph::new(&graph, &directions)
+        .ok_or(anyhow!("network turns towards a missing node"))?;
+    if let Some(steps) = options.steps {
+        for start in (0..dense.names.len() as u32).filter(|&node| is_start(dense.name(node))) {
+            println!(
+                "After {steps} steps {} is at {}",
+                dense.name(start),
+                dense.name(dense.position_after(start, steps))
+            );
+        }
+    }
+
+    let goal = &options.goal;
+    let starts: Vec<_> = dense
+        .names
+        .iter()
+        .filter(|name| options.start.matches(name))
+        .collect();
+    if starts.is_empty() {
+        println!("No node matches \"{}\"", options.start);
+    }
+    for start in starts {
+        match dense.count_steps(start, &|name| goal.matches(name)) {
+            Some(steps) => println!("Number of steps from \"{start}\" to \"{goal}\": {steps}"),
+            None => println!("\"{start}\" never reaches \"{goal}\""),
+        }
+    }
+    if graph.starts(&is_start).next().is_none() {
+        println!("No node matches \"{}\"", options.ghost_start);
+    } else {
+        match dense.count_steps_multiple(&is_start, &is_goal) {
+            Some(steps) => {
+                println!("Fewest steps for each path to reach their destination at once: {steps}")
+            }
+            None => println!("Some ghost never reaches \"{}\"", options.ghost_goal),
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{parse, DenseGraph, Direction, Options, Pattern};
+    use anyhow::{anyhow, Result};
+    use std::collections::HashSet;
+
+    const GHOSTS: &str = "\
+        LR\n\
+        \n\
+        11A = (11B, XXX)\n\
+        11B = (XXX, 11Z)\n\
+        11Z = (11B, XXX)\n\
+        22A = (22B, XXX)\n\
+        22B = (22C, 22C)\n\
+        22C = (22Z, 22Z)\n\
+        22Z = (22B, 22B)\n\
+        XXX = (XXX, XXX)";
+
+    #[test]
+    fn patterns() {
+        let matches = |pattern: &str, name: &str| Pattern::new(pattern).matches(name);
+
+        assert!(matches("??A", "11A"));
+        assert!(!matches("??A", "11Z"));
+        assert!(!matches("??A", "1A"));
+        assert!(!matches("??A", "111A"));
+
+        assert!(matches("*A", "A"));
+        assert!(matches("*A", "AAA"));
+        assert!(matches("*A", "LONGNAMEA"));
+        assert!(!matches("*A", "AAZ"));
+        assert!(matches("A*", "AZZ"));
+        assert!(!matches("A*", "ZAA"));
+        assert!(matches("*", ""));
+        assert!(matches("*", "ANYTHING"));
+
+        // A '*' that must give characters back to the rest of the pattern
+        assert!(matches("*B?D", "ABCBXD"));
+        assert!(matches("A*B*C", "AXBYBZC"));
+        assert!(!matches("A*B*C", "AXBYBZ"));
+        assert!(matches("?*?", "AB"));
+        assert!(!matches("?*?", "A"));
+
+        assert!(matches("", ""));
+        assert!(!matches("", "A"));
+        assert!(matches("AAA", "AAA"));
+        assert!(!matches("AAA", "AA"));
+        assert!(!matches("AAA", "AAAA"));
+    }
+
+    #[test]
+    fn options() -> Result<()> {
+        let options = Options::from_args(&[])?;
+        assert_eq!(options.start, Pattern::new("AAA"));
+        assert_eq!(options.goal, Pattern::new("ZZZ"));
+        assert_eq!(options.ghost_start, Pattern::new("*A"));
+
+        let options = Options::from_args(&["--goal", "?Z", "--start", "*A"])?;
+        assert_eq!(options.start, Pattern::new("*A"));
+        assert_eq!(options.goal, Pattern::new("?Z"));
+        assert_eq!(options.ghost_goal, Pattern::new("*Z"));
+
+        assert_eq!(options.steps, None);
+        assert_eq!(Options::from_args(&["--steps", "7"])?.steps, Some(7));
+
+        let options = Options::from_args(&["--dot", "network.dot", "--cycles"])?;
+        assert_eq!(options.dot.as_deref(), Some("network.dot"));
+        assert!(options.cycles);
+
+        assert!(Options::from_args(&["--start"]).is_err());
+        assert!(Options::from_args(&["AAA", "ZZZ"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn ghost_cycles() -> Result<()> {
+        let (directions, graph) = parse(GHOSTS)?;
+        assert_eq!(
+            graph.ghost_cycle(&directions, "11A"),
+            [("11B", Direction::Right), ("11Z", Direction::Left)]
+        );
+        assert_eq!(
+            graph.ghost_cycle(&directions, "22A"),
+            [
+                ("22B", Direction::Right),
+                ("22C", Direction::Left),
+                ("22Z", Direction::Right),
+                ("22B", Direction::Left),
+                ("22C", Direction::Right),
+                ("22Z", Direction::Left),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn dot() -> Result<()> {
+        let input = "\
+            L\n\
+            \n\
+            AAA = (BBB, ZZZ)\n\
+            BBB = (AAA, ZZZ)\n\
+            ZZZ = (ZZZ, ZZZ)";
+        let (directions, graph) = parse(input)?;
+        let highlighted: HashSet<_> = graph.ghost_cycle(&directions, "AAA").into_iter().collect();
+        let expected = [
+            "digraph network {",
+            "    node [shape=circle];",
+            "    \"AAA\" [shape=box, style=filled, fillcolor=palegreen];",
+            "    \"ZZZ\" [shape=doublecircle, style=filled, fillcolor=lightcoral];",
+            "    \"AAA\" -> \"BBB\" [label=\"L\", color=red, penwidth=2];",
+            "    \"AAA\" -> \"ZZZ\" [label=\"R\"];",
+            "    \"BBB\" -> \"AAA\" [label=\"L\", color=red, penwidth=2];",
+            "    \"BBB\" -> \"ZZZ\" [label=\"R\"];",
+            "    \"ZZZ\" -> \"ZZZ\" [label=\"LR\"];",
+            "}\n",
+        ]
+        .join("\n");
+        assert_eq!(
+            graph.to_dot(&|name| name == "AAA", &|name| name == "ZZZ", &highlighted),
+            expected
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reachability() -> Result<()> {
+        // AAA only ever turns left, so it circles with BBB and never reaches ZZZ
+        let input = "\
+            L\n\
+            \n\
+            AAA = (BBB, ZZZ)\n\
+            BBB = (AAA, ZZZ)\n\
+            ZZZ = (ZZZ, ZZZ)\n\
+            QQQ = (QQQ, AAA)";
+        let (directions, graph) = parse(input)?;
+        let is_start = |name: &str| name == "AAA";
+        let is_goal = |name: &str| name == "ZZZ";
+
+        assert_eq!(
+            graph.reachable_goals(&is_start, &is_goal),
+            [("AAA", vec!["ZZZ"])]
+        );
+        assert_eq!(graph.unreachable(&is_start), ["QQQ"]);
+        let mut components = graph.strongly_connected_components();
+        components.sort_unstable();
+        assert_eq!(components, [vec!["AAA", "BBB"], vec!["QQQ"], vec!["ZZZ"]]);
+
+        let dense = DenseGraph::new(&graph, &directions).ok_or(anyhow!("missing node"))?;
+        assert_eq!(dense.count_steps("AAA", &is_goal), None);
+        assert_eq!(dense.count_steps("QQQ", &is_goal), None);
+        Ok(())
+    }
+
+    #[test]
+    fn count_steps() -> Result<()> {
+        let input = "\
+            LLR\n\
+            \n\
+            AAA = (BBB, BBB)\n\
+            BBB = (AAA, ZZZ)\n\
+            ZZZ = (ZZZ, ZZZ)";
+        let (directions, graph) = parse(input)?;
+        let dense = DenseGraph::new(&graph, &directions).ok_or(anyhow!("missing node"))?;
+        assert_eq!(dense.count_steps("AAA", &|name| name == "ZZZ"), Some(6));
+        assert_eq!(graph.strongly_connected_components().len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn jump_table_matches_walk() -> Result<()> {
+        let (directions, graph) = parse(GHOSTS)?;
+        let dense = DenseGraph::new(&graph, &directions).ok_or(anyhow!("missing node"))?;
+
+        for start in ["11A", "22A"] {
+            let start = dense.id(start).ok_or(anyhow!("missing start"))?;
+            let mut node = start;
+            for steps in 0..50 {
+                assert_eq!(dense.position_after(start, steps), node);
+                node = dense.turn(node, directions[steps as usize % directions.len()]);
+            }
+        }
+        let start = dense.id("22A").ok_or(anyhow!("missing start"))?;
+        assert_eq!(
+            dense.name(dense.position_after(start, 3_000_000_000)),
+            "22Z"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn count_steps_multiple() -> Result<()> {
+        let is_start = |name: &str| name.ends_with('A');
+        let (directions, graph) = parse(GHOSTS)?;
+        let dense = DenseGraph::new(&graph, &directions).ok_or(anyhow!("missing node"))?;
+        assert_eq!(
+            dense.count_steps_multiple(&is_start, &|name| name.ends_with('Z')),
+            Some(6)
+        );
+        // 22A loops through 22Z but never through 11Z
+        assert_eq!(
+            dense.count_steps_multiple(&is_start, &|name| name == "11Z"),
+            None
+        );
+        assert_eq!(
+            dense.count_steps_multiple(&|name| name == "11A", &|name| name == "11Z"),
+            Some(2)
+        );
+
+        // A ghost circling between two nodes with no goal at all
+        let input = "\
+            L\n\
+            \n\
+            AAA = (BBB, BBB)\n\
+            BBB = (AAA, AAA)";
+        let (directions, graph) = parse(input)?;
+        let dense = DenseGraph::new(&graph, &directions).ok_or(anyhow!("missing node"))?;
+        assert_eq!(
+            dense.count_steps_multiple(&is_start, &|name| name.ends_with('Z')),
+            None
+        );
+        Ok(())
+    }
+}
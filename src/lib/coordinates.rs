@@ -1,4 +1,4 @@
-use std::ops::Sub;
+use std::{fmt::Display, ops::Sub};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Offset {
@@ -18,6 +18,12 @@ pub struct Coordinate {
     pub y: usize,
 }
 
+impl Display for Coordinate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
 impl From<(usize, usize)> for Coordinate {
     fn from(value: (usize, usize)) -> Self {
         Self {
@@ -67,8 +73,19 @@ impl Coordinate {
         }
     }
 
-    pub fn manhatten_distance(self, other: Self) -> usize {
-        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
+    pub fn manhatten_distance(self, other: Self) -> Option<usize> {
+        self.x
+            .abs_diff(other.x)
+            .checked_add(self.y.abs_diff(other.y))
+    }
+
+    pub fn chebyshev_distance(self, other: Self) -> usize {
+        self.x.abs_diff(other.x).max(self.y.abs_diff(other.y))
+    }
+
+    pub fn euclidean_distance_squared(self, other: Self) -> Option<usize> {
+        let dx = self.x.abs_diff(other.x).checked_pow(2)?;
+        dx.checked_add(self.y.abs_diff(other.y).checked_pow(2)?)
     }
 }
 
@@ -116,6 +133,12 @@ impl<T> Grid<T> {
             .get(coordinate.y)
             .and_then(|row| row.get(coordinate.x))
     }
+
+    pub fn get_mut(&mut self, coordinate: Coordinate) -> Option<&mut T> {
+        self.0
+            .get_mut(coordinate.y)
+            .and_then(|row| row.get_mut(coordinate.x))
+    }
 }
 
 impl<T: PartialEq> Grid<T> {
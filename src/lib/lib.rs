@@ -1 +1,3 @@
 pub mod coordinates;
+pub mod random;
+pub mod solution;
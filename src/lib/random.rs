@@ -0,0 +1,39 @@
+// Seeded xorshift generator, so synthetic inputs are the same on every run
+#[derive(Debug, Clone)]
+pub struct XorShift(u64);
+
+impl XorShift {
+    pub fn new(seed: u64) -> Self {
+        // A zero state would only ever produce zeros
+        Self(seed | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    // Value in 0..bound
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::XorShift;
+
+    #[test]
+    fn repeatable() {
+        let mut x = XorShift::new(7);
+        let mut y = XorShift::new(7);
+        for _ in 0..100 {
+            assert_eq!(x.next_u64(), y.next_u64());
+            assert!(x.below(13) < 13);
+            y.below(13);
+        }
+        assert_ne!(XorShift::new(0).next_u64(), 0);
+    }
+}
@@ -0,0 +1,147 @@
+use num::{BigUint, ToPrimitive};
+use std::{fmt::Display, iter::Sum, ops::Add};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[error("overflow while computing {0}")]
+pub struct Overflow(pub &'static str);
+
+#[derive(Debug, Clone)]
+pub enum Answer {
+    Small(u128),
+    Big(BigUint),
+}
+
+impl Answer {
+    // Shrink big answers back into a u128 when they fit
+    pub fn normalize(self) -> Self {
+        match self {
+            Self::Big(x) => x.to_u128().map_or(Self::Big(x), Self::Small),
+            small => small,
+        }
+    }
+}
+
+// Compares by value, so a big answer equals a small one holding the same number
+impl PartialEq for Answer {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Small(x), Self::Small(y)) => x == y,
+            (Self::Big(x), Self::Big(y)) => x == y,
+            (Self::Small(x), Self::Big(y)) | (Self::Big(y), Self::Small(x)) => {
+                BigUint::from(*x) == *y
+            }
+        }
+    }
+}
+
+impl Eq for Answer {}
+
+impl From<u128> for Answer {
+    fn from(value: u128) -> Self {
+        Self::Small(value)
+    }
+}
+
+impl From<u64> for Answer {
+    fn from(value: u64) -> Self {
+        Self::Small(value.into())
+    }
+}
+
+impl From<usize> for Answer {
+    fn from(value: usize) -> Self {
+        Self::Small(value as u128)
+    }
+}
+
+impl From<BigUint> for Answer {
+    fn from(value: BigUint) -> Self {
+        Self::Big(value).normalize()
+    }
+}
+
+impl From<Answer> for BigUint {
+    fn from(value: Answer) -> Self {
+        match value {
+            Answer::Small(x) => x.into(),
+            Answer::Big(x) => x,
+        }
+    }
+}
+
+impl Add for Answer {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        match (self, rhs) {
+            (Self::Small(x), Self::Small(y)) => x
+                .checked_add(y)
+                .map_or_else(|| Self::Big(BigUint::from(x) + y), Self::Small),
+            (x, y) => Self::Big(BigUint::from(x) + BigUint::from(y)).normalize(),
+        }
+    }
+}
+
+impl Sum for Answer {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::Small(0), Add::add)
+    }
+}
+
+impl Display for Answer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Small(x) => write!(f, "{x}"),
+            Self::Big(x) => write!(f, "{x}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Answer;
+    use num::BigUint;
+
+    #[test]
+    fn equality() {
+        assert_eq!(Answer::Big(5u32.into()), Answer::Small(5));
+        assert_eq!(Answer::Small(5), Answer::Big(5u32.into()));
+        assert_ne!(
+            Answer::Big(BigUint::from(u128::MAX) + 1u32),
+            Answer::Small(0)
+        );
+    }
+
+    #[test]
+    fn normalize() {
+        assert!(matches!(
+            Answer::Big(5u32.into()).normalize(),
+            Answer::Small(5)
+        ));
+        let big = BigUint::from(u128::MAX) + 1u32;
+        assert!(matches!(Answer::Big(big.clone()).normalize(), Answer::Big(x) if x == big));
+        assert!(matches!(
+            Answer::from(BigUint::from(7u32)),
+            Answer::Small(7)
+        ));
+    }
+
+    #[test]
+    fn add_promotes_on_overflow() {
+        let sum = Answer::Small(u128::MAX) + Answer::Small(1);
+        let expected = BigUint::from(u128::MAX) + 1u32;
+        assert!(matches!(&sum, Answer::Big(x) if *x == expected));
+        assert_eq!(sum.to_string(), expected.to_string());
+
+        assert!(matches!(
+            Answer::Big(2u32.into()) + Answer::Small(3),
+            Answer::Small(5)
+        ));
+        let total: Answer = [u128::MAX, u128::MAX, 2]
+            .into_iter()
+            .map(Answer::from)
+            .sum();
+        assert_eq!(BigUint::from(total), BigUint::from(u128::MAX) * 2u32 + 2u32);
+    }
+}